@@ -2,9 +2,13 @@
 <tileset version="1.8" tiledversion="1.8.5" name="Sokoban" tilewidth="128" tileheight="128" spacing="20" tilecount="104" columns="13" objectalignment="topleft">
  <properties>
   <property name="player_down" type="int" value="52"/>
+  <property name="player_down_push" type="int" value="65"/>
   <property name="player_left" type="int" value="78"/>
+  <property name="player_left_push" type="int" value="91"/>
   <property name="player_right" type="int" value="81"/>
+  <property name="player_right_push" type="int" value="94"/>
   <property name="player_up" type="int" value="55"/>
+  <property name="player_up_push" type="int" value="68"/>
  </properties>
  <image source="../sprites/Tilesheet/sokoban.png" width="1904" height="1164"/>
  <tile id="6" type="crate">
@@ -58,6 +62,16 @@
   </animation>
  </tile>
  <tile id="11" type="hole"/>
+ <tile id="25" type="paint">
+  <properties>
+   <property name="paints" type="int" value="1"/>
+  </properties>
+ </tile>
+ <tile id="38" type="paint">
+  <properties>
+   <property name="paints" type="int" value="2"/>
+  </properties>
+ </tile>
  <tile id="39" type="goal">
   <animation>
    <frame tileid="39" duration="500"/>
@@ -109,6 +123,11 @@
    <frame tileid="13" duration="500"/>
   </animation>
  </tile>
+ <tile id="51" type="paint">
+  <properties>
+   <property name="paints" type="int" value="3"/>
+  </properties>
+ </tile>
  <tile id="52">
   <animation>
    <frame tileid="52" duration="500"/>
@@ -125,7 +144,33 @@
    <frame tileid="57" duration="500"/>
   </animation>
  </tile>
+ <tile id="64" type="paint">
+  <properties>
+   <property name="paints" type="int" value="4"/>
+  </properties>
+ </tile>
+ <tile id="65">
+  <animation>
+   <frame tileid="65" duration="500"/>
+   <frame tileid="66" duration="500"/>
+   <frame tileid="65" duration="500"/>
+   <frame tileid="67" duration="500"/>
+  </animation>
+ </tile>
+ <tile id="68">
+  <animation>
+   <frame tileid="68" duration="500"/>
+   <frame tileid="69" duration="500"/>
+   <frame tileid="68" duration="500"/>
+   <frame tileid="70" duration="500"/>
+  </animation>
+ </tile>
  <tile id="72" type="spawn"/>
+ <tile id="77" type="paint">
+  <properties>
+   <property name="paints" type="int" value="5"/>
+  </properties>
+ </tile>
  <tile id="78">
   <animation>
    <frame tileid="78" duration="500"/>
@@ -146,4 +191,20 @@
  <tile id="85" type="solid"/>
  <tile id="86" type="solid"/>
  <tile id="87" type="solid"/>
+ <tile id="91">
+  <animation>
+   <frame tileid="91" duration="500"/>
+   <frame tileid="92" duration="500"/>
+   <frame tileid="91" duration="500"/>
+   <frame tileid="93" duration="500"/>
+  </animation>
+ </tile>
+ <tile id="94">
+  <animation>
+   <frame tileid="94" duration="500"/>
+   <frame tileid="95" duration="500"/>
+   <frame tileid="94" duration="500"/>
+   <frame tileid="96" duration="500"/>
+  </animation>
+ </tile>
 </tileset>
@@ -5,6 +5,7 @@
 use std::{
     fs::File,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
 use serde::Deserialize;
@@ -13,9 +14,12 @@ use sfml::{
     graphics::{Color, Font},
     SfBox,
 };
-use tiled::{Loader, Map};
+use tiled::{Loader, Map, Tileset};
 
-use crate::graphics::Tilesheet;
+use crate::{context::SaveData, graphics::Tilesheet};
+
+mod theme;
+pub use theme::*;
 
 pub const MOVE_SOUND_DIR: &str = "assets/sound/move";
 pub const UNDO_SOUND_DIR: &str = "assets/sound/undo";
@@ -41,12 +45,14 @@ pub struct AssetManager {
     pub tilesheet: Tilesheet,
     pub win_font: SfBox<Font>,
     pub play_overlay_map: Map,
+    /// The themes the player can choose from. There is always at least one.
+    pub themes: Vec<Theme>,
     total_level_count: usize,
 }
 
-impl AssetManager {
-    /// Creates a new asset manager and loads the data it references.
-    pub fn load() -> anyhow::Result<Self> {
+impl LevelCategory {
+    /// Loads every level category along with their maps. Does not load any textures or sounds.
+    pub fn load_all() -> anyhow::Result<Vec<Self>> {
         #[derive(Deserialize)]
         pub struct RonLevelCategory {
             pub name: String,
@@ -58,6 +64,12 @@ impl AssetManager {
             type Error = anyhow::Error;
 
             fn try_from(value: RonLevelCategory) -> Result<Self, Self::Error> {
+                anyhow::ensure!(
+                    !value.maps.is_empty(),
+                    "level category {:?} has no levels",
+                    value.name
+                );
+
                 Ok(LevelCategory {
                     name: value.name,
                     color: Color::from(value.color),
@@ -76,21 +88,43 @@ impl AssetManager {
         let level_categories: Vec<RonLevelCategory> =
             ron::de::from_reader(File::open("assets/levels/levels.ron")?)?;
 
-        let level_categories = level_categories
+        level_categories
             .into_iter()
             .map(|lvl| lvl.try_into())
-            .collect::<Result<Vec<LevelCategory>, _>>()?;
+            .collect::<Result<Vec<LevelCategory>, _>>()
+    }
+
+    /// Returns whether the level with the given index can be played; i.e. if it or the one before
+    /// it have been completed.
+    pub fn is_unlocked(&self, level: usize, save_data: &SaveData) -> bool {
+        let is_completed = |level: usize| save_data.internal_set().contains(&self.maps[level].1);
+
+        level == 0 || is_completed(level) || is_completed(level - 1)
+    }
+}
+
+impl AssetManager {
+    /// Creates a new asset manager and loads the data it references.
+    pub fn load() -> anyhow::Result<Self> {
+        let level_categories = LevelCategory::load_all()?;
 
         let play_overlay_map = Loader::new().load_tmx_map(Path::new(PLAY_OVERLAY_PATH))?;
 
-        let map = Loader::new().load_tmx_map(Path::new("assets/levels/test.tmx"))?;
+        let themes: Vec<RonTheme> = ron::de::from_reader(File::open(THEMES_PATH)?)?;
+        let themes = themes
+            .into_iter()
+            .map(|theme| theme.try_into())
+            .collect::<Result<Vec<Theme>, _>>()?;
+        anyhow::ensure!(!themes.is_empty(), "no themes defined in {}", THEMES_PATH);
+
         Ok(Self {
-            tilesheet: Tilesheet::from_tileset(map.tilesets().first().unwrap().clone())?,
+            tilesheet: Tilesheet::from_tileset(Self::load_level_tileset()?)?,
             main_menu: Loader::new().load_tmx_map(Path::new(MAIN_MENU_PATH))?,
             icon_tilesheet: Tilesheet::from_file(Path::new(ICON_TILESHEET_PATH))?,
             total_level_count: level_categories.iter().flat_map(|c| c.maps.iter()).count(),
             level_categories,
             play_overlay_map,
+            themes,
             ui_click_sound: SoundBuffer::from_file(UI_CLICK_SOUND_PATH)
                 .expect("could not load ui click sfx"),
             walk_sounds: std::fs::read_dir(Path::new(MOVE_SOUND_DIR))
@@ -121,6 +155,12 @@ impl AssetManager {
         })
     }
 
+    /// Loads the tileset levels are made out of.
+    pub fn load_level_tileset() -> anyhow::Result<Arc<Tileset>> {
+        let map = Loader::new().load_tmx_map(Path::new("assets/levels/test.tmx"))?;
+        Ok(map.tilesets().first().unwrap().clone())
+    }
+
     /// Get a reference to the asset manager's total level count.
     pub fn total_level_count(&self) -> usize {
         self.total_level_count
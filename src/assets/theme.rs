@@ -0,0 +1,83 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::Context as _;
+use serde::Deserialize;
+use sfml::{
+    graphics::{Color, Font},
+    SfBox,
+};
+
+use crate::graphics::Tilesheet;
+
+pub const THEMES_PATH: &str = "assets/themes.ron";
+
+/// A set of visual overrides that are swapped as a unit. Anything a theme does not set falls back
+/// to what the maps and default assets use.
+pub struct Theme {
+    pub name: String,
+    /// Replaces the level tilesheet. Must share the layout of the default one, since maps refer
+    /// to its tiles by id.
+    pub tilesheet: Option<Tilesheet>,
+    /// Replaces the font used for text.
+    pub font: Option<SfBox<Font>>,
+    /// Replaces the background color of levels and menus.
+    pub background_color: Option<Color>,
+    /// Replaces the color of level categories, by category name.
+    pub category_colors: HashMap<String, Color>,
+    /// The color of text drawn over levels and in menus built in code.
+    pub text_color: Color,
+    /// The color used to highlight focused UI elements.
+    pub accent_color: Color,
+    /// The color of the bars around levels drawn pixel-perfect.
+    pub letterbox_color: Color,
+}
+
+#[derive(Deserialize)]
+pub(super) struct RonTheme {
+    name: String,
+    #[serde(default)]
+    tilesheet: Option<String>,
+    #[serde(default)]
+    font: Option<String>,
+    #[serde(default)]
+    background_color: Option<u32>,
+    #[serde(default)]
+    category_colors: HashMap<String, u32>,
+    #[serde(default)]
+    text_color: Option<u32>,
+    #[serde(default)]
+    accent_color: Option<u32>,
+    #[serde(default)]
+    letterbox_color: Option<u32>,
+}
+
+impl TryFrom<RonTheme> for Theme {
+    type Error = anyhow::Error;
+
+    fn try_from(value: RonTheme) -> Result<Self, Self::Error> {
+        Ok(Theme {
+            tilesheet: value
+                .tilesheet
+                .map(|path| Tilesheet::from_file(Path::new(&path)))
+                .transpose()
+                .with_context(|| format!("could not load tilesheet of theme {}", value.name))?,
+            font: value
+                .font
+                .map(|path| {
+                    Font::from_file(&path)
+                        .with_context(|| format!("could not load font of theme {}", value.name))
+                })
+                .transpose()?,
+            background_color: value.background_color.map(Color::from),
+            category_colors: value
+                .category_colors
+                .into_iter()
+                .map(|(category, color)| (category, Color::from(color)))
+                .collect(),
+            text_color: value.text_color.map_or(Color::WHITE, Color::from),
+            accent_color: value.accent_color.map_or(Color::WHITE, Color::from),
+            letterbox_color: value.letterbox_color.map_or(Color::BLACK, Color::from),
+            name: value.name,
+        })
+    }
+}
@@ -0,0 +1,5 @@
+fn main() {
+    if let Err(err) = sokoban::tty::run() {
+        println!("Fatal error: {}", err)
+    }
+}
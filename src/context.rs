@@ -1,14 +1,154 @@
+mod config;
+pub use config::*;
 mod save_data;
 pub use save_data::*;
+mod thumbnails;
+pub use thumbnails::*;
 
-use std::time::Duration;
+use std::{
+    collections::HashMap,
+    fs::File,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use crate::{assets::AssetManager, input_system::InputSystem, sound_manager::SoundManager};
+use serde::Serialize;
+use sfml::graphics::{Color, Font};
+
+use crate::{
+    assets::{AssetManager, Theme},
+    graphics::Tilesheet,
+    input_system::InputSystem,
+    sound_manager::SoundManager,
+};
 
 pub struct Context<'assets> {
     pub assets: &'assets AssetManager,
     pub sound: SoundManager<'assets>,
+    pub config: Config,
     pub completed_levels: SaveData,
     pub delta_time: Duration,
     pub input: InputSystem,
+    /// The zoom chosen for each level during this session.
+    pub level_zoom: HashMap<PathBuf, f32>,
+    pub thumbnails: Thumbnails,
+    /// The index of the active theme in the asset manager.
+    theme: usize,
+}
+
+impl<'assets> Context<'assets> {
+    /// Creates a context without any thumbnails loaded; see [`Thumbnails::load`].
+    pub fn new(
+        assets: &'assets AssetManager,
+        config: Config,
+        completed_levels: SaveData,
+        input: InputSystem,
+    ) -> Self {
+        let theme = config
+            .theme
+            .as_ref()
+            .and_then(|name| assets.themes.iter().position(|t| &t.name == name))
+            .unwrap_or(0);
+
+        Self {
+            assets,
+            sound: SoundManager::new(),
+            config,
+            completed_levels,
+            delta_time: Duration::default(),
+            input,
+            level_zoom: HashMap::new(),
+            thumbnails: Thumbnails::default(),
+            theme,
+        }
+    }
+
+    pub fn theme(&self) -> &'assets Theme {
+        &self.assets.themes[self.theme]
+    }
+
+    pub fn theme_index(&self) -> usize {
+        self.theme
+    }
+
+    /// Makes the theme with the given index the active one, remembering it in the config file.
+    pub fn set_theme(&mut self, theme: usize) {
+        if theme == self.theme || theme >= self.assets.themes.len() {
+            return;
+        }
+
+        self.theme = theme;
+        self.config.theme = Some(self.theme().name.clone());
+        self.config.save();
+        // Thumbnails are rendered with the active theme's tilesheet and colors
+        self.thumbnails = Thumbnails::load(self);
+    }
+
+    /// The tilesheet levels are drawn with.
+    pub fn tilesheet(&self) -> &'assets Tilesheet {
+        self.theme()
+            .tilesheet
+            .as_ref()
+            .unwrap_or(&self.assets.tilesheet)
+    }
+
+    pub fn font(&self) -> &'assets Font {
+        self.theme()
+            .font
+            .as_deref()
+            .unwrap_or(&self.assets.win_font)
+    }
+
+    /// The background color to use instead of the given one, which is usually set by a map.
+    pub fn background_color(&self, map_color: Color) -> Color {
+        self.theme().background_color.unwrap_or(map_color)
+    }
+
+    /// How fast animations play according to the config, relative to their default speed.
+    pub fn animation_speed(&self) -> f32 {
+        self.config.animation_speed.clamp(0.1, 10.)
+    }
+
+    /// Whether levels drawn with the given tilesheet are drawn pixel-perfect, according to the
+    /// config or, by default, whether the tilesheet is pixel art.
+    pub fn pixel_perfect(&self, tilesheet: &Tilesheet) -> bool {
+        self.config
+            .pixel_perfect
+            .unwrap_or_else(|| tilesheet.is_pixel_art())
+    }
+
+    /// The color of the level category with the given index.
+    pub fn category_color(&self, category: usize) -> Color {
+        let category = &self.assets.level_categories[category];
+        self.theme()
+            .category_colors
+            .get(&category.name)
+            .copied()
+            .unwrap_or(category.color)
+    }
+}
+
+/// Writes a value to a RON file, creating the directories up to it, and logs any failure instead
+/// of returning it. `what` describes the contents of the file in log messages, e.g. "config".
+pub(crate) fn save_ron_file(value: &impl Serialize, path: &Path, what: &str) {
+    if let Err(err) = std::fs::create_dir_all(path.parent().unwrap()) {
+        log::error!(
+            "could not create the directory of the {} file: {}",
+            what,
+            err
+        );
+        return;
+    }
+    let file = match File::create(path) {
+        Ok(file) => file,
+        Err(err) => {
+            log::error!("could not create {} file: {}", what, err);
+            return;
+        }
+    };
+    if let Err(err) = ron::ser::to_writer_pretty(file, value, Default::default()) {
+        log::error!("could not save {}: {}", what, err);
+    } else {
+        log::info!("updated {} file at {:?}", what, path);
+    }
 }
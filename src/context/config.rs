@@ -0,0 +1,80 @@
+use std::{fs::File, path::PathBuf};
+
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use sfml::system::Vector2u;
+
+/// The player's preferences, changed through the settings screen. Anything missing from the
+/// config file is left at its default.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct Config {
+    /// The name of the active theme, or `None` to use the first one available.
+    pub theme: Option<String>,
+    /// The volume of sounds made by levels, such as walking and undoing, from 0 to 100.
+    pub sound_volume: f32,
+    /// The volume of sounds made by menus, from 0 to 100.
+    pub ui_volume: f32,
+    pub fullscreen: bool,
+    /// The size of the window while not in fullscreen, which is kept up to date as it is resized.
+    pub window_size: Vector2u,
+    pub vsync: bool,
+    /// The maximum amount of frames drawn per second, or 0 for no limit.
+    pub frame_cap: u32,
+    /// How fast everything is animated, from movement and particles to transitions, relative to
+    /// the default.
+    pub animation_speed: f32,
+    /// Whether levels are only scaled by whole numbers, with sprites placed on whole pixels, or
+    /// `None` to do so only for tilesheets marked as pixel art. Textures of pixel-art tilesheets
+    /// are never smoothed either way.
+    pub pixel_perfect: Option<bool>,
+    /// Whether changes are kept in memory only instead of being written to the config file.
+    #[serde(skip)]
+    in_memory: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            theme: None,
+            sound_volume: 40.,
+            ui_volume: 60.,
+            fullscreen: false,
+            window_size: Vector2u::new(1080, 720),
+            vsync: true,
+            frame_cap: 0,
+            animation_speed: 1.,
+            pixel_perfect: None,
+            in_memory: false,
+        }
+    }
+}
+
+impl Config {
+    pub fn from_config_file() -> anyhow::Result<Self> {
+        Ok(ron::de::from_reader::<_, Self>(File::open(
+            Self::config_file_path(),
+        )?)?)
+    }
+
+    /// Stops writing changes to this config to the config file, e.g. while playing back a
+    /// recording.
+    pub fn keep_in_memory(&mut self) {
+        self.in_memory = true;
+    }
+
+    pub fn save(&self) {
+        if self.in_memory {
+            return;
+        }
+
+        super::save_ron_file(self, &Self::config_file_path(), "config");
+    }
+
+    pub fn config_file_path() -> PathBuf {
+        ProjectDirs::from("", "rusty-pizza", env!("CARGO_PKG_NAME"))
+            .expect("could not obtain project directories")
+            .config_dir()
+            .join("config.ron")
+    }
+}
@@ -1,11 +1,21 @@
-use std::{collections::HashSet, fs::File, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    path::{Path, PathBuf},
+};
 
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Serialize, Default)]
+#[derive(Deserialize, Serialize, Default, Clone)]
 pub struct SaveData {
     set: HashSet<PathBuf>,
+    /// The best star rating obtained on each level that has a par.
+    #[serde(default)]
+    stars: HashMap<PathBuf, u8>,
+    /// Whether changes are kept in memory only instead of being written to the save file.
+    #[serde(skip)]
+    in_memory: bool,
 }
 
 impl SaveData {
@@ -15,6 +25,12 @@ impl SaveData {
         )?)?)
     }
 
+    /// Stops writing changes to this save data to the save file, e.g. while playing back a
+    /// recording.
+    pub fn keep_in_memory(&mut self) {
+        self.in_memory = true;
+    }
+
     /// Get a reference to the level completion db's internal set.
     pub fn internal_set(&self) -> &HashSet<PathBuf> {
         &self.set
@@ -26,23 +42,31 @@ impl SaveData {
         }
 
         self.set.insert(level_completed);
-        let path_to_save_to = Self::save_file_path();
-        std::fs::create_dir_all(path_to_save_to.parent().unwrap())
-            .expect("could not create dirs up to project data dir");
-        let file = match File::create(&path_to_save_to) {
-            Ok(file) => file,
-            Err(err) => {
-                log::error!("could not create save file: {}", err);
-                return;
-            }
-        };
-        if let Err(err) = ron::ser::to_writer(file, &self) {
-            log::error!("could not save progress: {}", err);
-        } else {
-            log::info!("updated savefile at {:?}", path_to_save_to);
+        self.save();
+    }
+
+    /// Get the best star rating obtained on a level, if it has been rated.
+    pub fn stars(&self, level: &Path) -> Option<u8> {
+        self.stars.get(level).copied()
+    }
+
+    /// Records a star rating for a level, keeping the best one obtained so far.
+    pub fn record_stars(&mut self, level: PathBuf, stars: u8) {
+        let best = self.stars.entry(level).or_insert(0);
+        if stars > *best {
+            *best = stars;
+            self.save();
         }
     }
 
+    fn save(&self) {
+        if self.in_memory {
+            return;
+        }
+
+        super::save_ron_file(self, &Self::save_file_path(), "save");
+    }
+
     pub fn save_file_path() -> PathBuf {
         ProjectDirs::from("", "rusty-pizza", env!("CARGO_PKG_NAME"))
             .expect("could not obtain project directories")
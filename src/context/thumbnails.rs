@@ -0,0 +1,178 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use anyhow::Context as _;
+use directories::ProjectDirs;
+use sfml::{
+    graphics::{Image, IntRect, Texture},
+    SfBox,
+};
+use tiled::Map;
+
+use crate::level::Level;
+
+use super::Context;
+
+/// Miniatures of every level, rendered once and cached on disk until their map is modified.
+#[derive(Default)]
+pub struct Thumbnails {
+    textures: HashMap<PathBuf, SfBox<Texture>>,
+}
+
+impl Thumbnails {
+    /// The length of the longest side of thumbnails, in pixels.
+    pub const SIZE: u32 = 256;
+
+    /// Loads the thumbnail of every level in the asset manager, rendering the ones that have not
+    /// been cached yet or whose map changed since.
+    pub fn load(ctx: &Context) -> Self {
+        let mut textures = HashMap::new();
+        for (map, path) in ctx
+            .assets
+            .level_categories
+            .iter()
+            .flat_map(|c| c.maps.iter())
+        {
+            match Self::load_or_render(ctx, map, path) {
+                Ok(texture) => {
+                    textures.insert(path.clone(), texture);
+                }
+                Err(err) => log::warn!("could not load thumbnail for {:?}: {}", path, err),
+            }
+        }
+
+        Self { textures }
+    }
+
+    /// Get the thumbnail of a level, if it could be loaded.
+    pub fn get(&self, level: &Path) -> Option<&Texture> {
+        self.textures.get(level).map(|texture| &**texture)
+    }
+
+    /// Renders the thumbnail of every level in the asset manager to a PNG file in the given
+    /// directory, named after the level's path.
+    pub fn export(ctx: &Context, dir: &Path) -> anyhow::Result<()> {
+        fs::create_dir_all(dir)?;
+        for (map, path) in ctx
+            .assets
+            .level_categories
+            .iter()
+            .flat_map(|c| c.maps.iter())
+        {
+            let image = render_thumbnail(ctx, map, Self::SIZE)
+                .with_context(|| format!("could not render thumbnail for {:?}", path))?;
+            let file_path = dir.join(format!("{}.png", Self::file_stem(path)));
+            save_image(&image, &file_path)?;
+            log::info!("exported thumbnail to {:?}", file_path);
+        }
+
+        Ok(())
+    }
+
+    fn load_or_render(ctx: &Context, map: &Map, path: &Path) -> anyhow::Result<SfBox<Texture>> {
+        let cache_path = Self::cache_path(&ctx.theme().name, path)?;
+        let cached_image = cache_path
+            .is_file()
+            .then(|| Image::from_file(cache_path.to_str()?))
+            .flatten();
+
+        let image = match cached_image {
+            Some(image) => image,
+            None => {
+                let image = render_thumbnail(ctx, map, Self::SIZE)?;
+                if let Err(err) = save_image(&image, &cache_path) {
+                    log::warn!("could not cache thumbnail: {}", err);
+                } else if let Err(err) = Self::remove_stale(&cache_path, path) {
+                    log::warn!("could not remove stale thumbnails: {}", err);
+                }
+                image
+            }
+        };
+
+        let mut texture = Texture::new().context("could not create thumbnail texture")?;
+        texture.load_from_image(&image, IntRect::default())?;
+        texture.set_smooth(true);
+
+        Ok(texture)
+    }
+
+    /// Where the thumbnail of a level is cached for a theme. Depends on when its map was last
+    /// modified, so that editing a level renders its thumbnail anew.
+    fn cache_path(theme: &str, level: &Path) -> anyhow::Result<PathBuf> {
+        let modified = fs::metadata(level)?
+            .modified()?
+            .duration_since(UNIX_EPOCH)?
+            .as_secs();
+
+        Ok(Self::cache_dir().join(theme).join(format!(
+            "{}-{}.png",
+            Self::file_stem(level),
+            modified
+        )))
+    }
+
+    /// Removes the thumbnails of a level cached next to the one at `cache_path` for earlier
+    /// versions of its map.
+    fn remove_stale(cache_path: &Path, level: &Path) -> anyhow::Result<()> {
+        let prefix = format!("{}-", Self::file_stem(level));
+        let Some(dir) = cache_path.parent() else {
+            return Ok(());
+        };
+
+        for entry in fs::read_dir(dir)? {
+            let entry_path = entry?.path();
+            let is_stale = entry_path != cache_path
+                && entry_path.extension().is_some_and(|ext| ext == "png")
+                && entry_path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str()?.strip_prefix(&prefix))
+                    .is_some_and(|modified| modified.parse::<u64>().is_ok());
+            if is_stale {
+                fs::remove_file(&entry_path)?;
+                log::info!("removed stale thumbnail {:?}", entry_path);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// A file name for the thumbnail of a level that is unique among all levels.
+    fn file_stem(level: &Path) -> String {
+        level
+            .strip_prefix("assets/levels")
+            .unwrap_or(level)
+            .with_extension("")
+            .iter()
+            .map(|component| component.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("_")
+    }
+
+    pub fn cache_dir() -> PathBuf {
+        ProjectDirs::from("", "rusty-pizza", env!("CARGO_PKG_NAME"))
+            .expect("could not obtain project directories")
+            .cache_dir()
+            .join("thumbnails")
+    }
+}
+
+/// Renders the thumbnail of a level map, with its longest side being `max_side` pixels long.
+pub fn render_thumbnail(ctx: &Context, map: &Map, max_side: u32) -> anyhow::Result<Image> {
+    Level::from_map(map, ctx)?
+        .render_thumbnail(max_side)
+        .context("could not render level")
+}
+
+fn save_image(image: &Image, path: &Path) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let path_str = path.to_str().context("path is not valid UTF-8")?;
+    anyhow::ensure!(image.save_to_file(path_str), "could not save {:?}", path);
+
+    Ok(())
+}
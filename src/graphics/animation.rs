@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use super::{SpriteAtlas, Tilesheet};
+
+/// A looping animation made out of frames, each shown for a set time. Frames are usually indices
+/// into a [`SpriteAtlas`].
+#[derive(Clone, Debug)]
+pub struct FrameAnimation {
+    /// Each frame and how long it is shown for.
+    frames: Vec<(usize, Duration)>,
+    length: Duration,
+}
+
+impl FrameAnimation {
+    /// Adds the frames of a tile's Tiled animation to a sprite atlas, returning the animation that
+    /// plays them. Tiles without an animation result in a single frame animation.
+    pub fn load_tile_into_atlas(
+        tilesheet: &Tilesheet,
+        id: u32,
+        atlas: &mut SpriteAtlas,
+    ) -> Option<Self> {
+        Self::from_tile(tilesheet, id, |tile_id| {
+            atlas.add_frame(tilesheet.tile_rect(tile_id)?);
+            Some(atlas.frame_count() - 1)
+        })
+    }
+
+    /// Creates an animation that plays the frames of a tile's Tiled animation, where frames are
+    /// the ids of the tiles shown. Tiles without an animation result in a single frame animation.
+    pub fn from_tile_ids(tilesheet: &Tilesheet, id: u32) -> Self {
+        Self::from_tile(tilesheet, id, |tile_id| Some(tile_id as usize))
+            .expect("tile ids are always valid frames")
+    }
+
+    /// Creates an animation out of the frames of a tile's Tiled animation, mapping each frame's
+    /// tile id into the frame to show through `add_frame`.
+    fn from_tile(
+        tilesheet: &Tilesheet,
+        id: u32,
+        mut add_frame: impl FnMut(u32) -> Option<usize>,
+    ) -> Option<Self> {
+        let tile = tilesheet.tileset().get_tile(id);
+        let tile_frames = match tile.as_ref().and_then(|t| t.animation.as_ref()) {
+            Some(animation) if !animation.is_empty() => animation
+                .iter()
+                .map(|frame| (frame.tile_id, Duration::from_millis(frame.duration as u64)))
+                .collect(),
+            _ => vec![(id, Duration::ZERO)],
+        };
+
+        let mut frames = Vec::with_capacity(tile_frames.len());
+        for (tile_id, duration) in tile_frames {
+            frames.push((add_frame(tile_id)?, duration));
+        }
+
+        Some(Self {
+            length: frames.iter().map(|(_, duration)| *duration).sum(),
+            frames,
+        })
+    }
+
+    /// How long it takes to play every frame once.
+    pub fn length(&self) -> Duration {
+        self.length
+    }
+
+    /// Whether the animation has more than a single frame.
+    pub fn is_animated(&self) -> bool {
+        self.frames.len() > 1
+    }
+
+    /// The frame to show after the animation has been playing for the given time.
+    pub fn frame_at(&self, time: Duration) -> usize {
+        if self.length.is_zero() {
+            return self.frames[0].0;
+        }
+
+        let mut time_left = Duration::from_nanos((time.as_nanos() % self.length.as_nanos()) as u64);
+        for &(frame, duration) in self.frames.iter() {
+            if time_left < duration {
+                return frame;
+            }
+            time_left -= duration;
+        }
+
+        self.frames[self.frames.len() - 1].0
+    }
+}
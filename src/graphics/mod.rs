@@ -1,8 +1,14 @@
 //! Graphics utilities, mostly for things related to sprites.
 
+mod animation;
+pub use animation::FrameAnimation;
+mod particles;
+pub use particles::{ParticleBurst, ParticleSystem};
 mod quadmesh;
 pub use quadmesh::QuadMeshable;
 mod sprite_atlas;
 pub use sprite_atlas::SpriteAtlas;
 mod tilesheet;
 pub use tilesheet::{Tilesheet, TilesheetLoadError};
+mod tween;
+pub use tween::PositionTween;
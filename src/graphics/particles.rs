@@ -0,0 +1,104 @@
+use std::{f32::consts::TAU, time::Duration};
+
+use rand::{prelude::SliceRandom, thread_rng, Rng};
+use sfml::{
+    graphics::{Color, Drawable, PrimitiveType, RenderStates, Vertex},
+    system::Vector2f,
+};
+
+/// Describes a burst of particles flying out of a point.
+#[derive(Clone, Copy, Debug)]
+pub struct ParticleBurst {
+    pub count: usize,
+    /// Each particle picks one of these colors at random.
+    pub colors: &'static [Color],
+    /// The range of speeds particles are emitted with, in pixels per second.
+    pub speed: (f32, f32),
+    pub lifetime: Duration,
+    /// The side length of each particle, in pixels.
+    pub size: f32,
+    /// Vertical acceleration applied to particles, in pixels per second squared.
+    pub gravity: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Particle {
+    position: Vector2f,
+    velocity: Vector2f,
+    color: Color,
+    size: f32,
+    gravity: f32,
+    age: Duration,
+    lifetime: Duration,
+}
+
+/// A set of short-lived, purely cosmetic particles, which shrink and fade out as they age.
+#[derive(Clone, Debug, Default)]
+pub struct ParticleSystem {
+    particles: Vec<Particle>,
+}
+
+impl ParticleSystem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Emits a burst of particles from the given position.
+    pub fn emit(&mut self, position: Vector2f, burst: &ParticleBurst) {
+        let mut rng = thread_rng();
+        for _ in 0..burst.count {
+            let angle = rng.gen_range(0. ..TAU);
+            let speed = rng.gen_range(burst.speed.0..=burst.speed.1);
+            self.particles.push(Particle {
+                position,
+                velocity: Vector2f::new(angle.cos(), angle.sin()) * speed,
+                color: *burst.colors.choose(&mut rng).unwrap_or(&Color::WHITE),
+                size: burst.size,
+                gravity: burst.gravity,
+                age: Duration::ZERO,
+                // Vary lifetimes a bit so bursts don't vanish all at once
+                lifetime: burst.lifetime.mul_f32(rng.gen_range(0.7..=1.)),
+            });
+        }
+    }
+
+    pub fn update(&mut self, delta: Duration) {
+        let seconds = delta.as_secs_f32();
+        for particle in self.particles.iter_mut() {
+            particle.age += delta;
+            particle.velocity.y += particle.gravity * seconds;
+            particle.position += particle.velocity * seconds;
+        }
+
+        self.particles.retain(|p| p.age < p.lifetime);
+    }
+}
+
+impl Drawable for ParticleSystem {
+    fn draw<'a: 'shader, 'texture, 'shader, 'shader_texture>(
+        &'a self,
+        target: &mut dyn sfml::graphics::RenderTarget,
+        states: &RenderStates<'texture, 'shader, 'shader_texture>,
+    ) {
+        let mut vertices = Vec::with_capacity(self.particles.len() * 4);
+        for particle in self.particles.iter() {
+            let life_left = 1. - particle.age.as_secs_f32() / particle.lifetime.as_secs_f32();
+            let half_size = particle.size * (0.5 + life_left * 0.5) / 2.;
+            let color = Color {
+                a: (particle.color.a as f32 * life_left) as u8,
+                ..particle.color
+            };
+
+            for corner in [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)] {
+                vertices.push(Vertex::with_pos_color(
+                    particle.position + Vector2f::new(corner.0, corner.1) * half_size,
+                    color,
+                ));
+            }
+        }
+
+        let mut states = *states;
+        states.set_texture(None);
+        target.draw_primitives(&vertices, PrimitiveType::QUADS, &states);
+    }
+}
@@ -6,6 +6,10 @@ use sfml::{
 /// Represents a container which can be expanded with quads made out of vertices.
 pub trait QuadMeshable {
     fn add_quad(&mut self, position: Vector2f, size: Vector2f, uv: FloatRect);
+    /// Changes the UVs of the quad with the given index, in the order quads were added.
+    fn set_quad_uv(&mut self, quad: usize, uv: FloatRect);
+    /// Changes the color of the quad with the given index, in the order quads were added.
+    fn set_quad_color(&mut self, quad: usize, color: Color);
 }
 
 impl QuadMeshable for Vec<Vertex> {
@@ -31,4 +35,18 @@ impl QuadMeshable for Vec<Vertex> {
             Vector2f::new(uv.left, uv.top + uv.height),
         ));
     }
+
+    fn set_quad_uv(&mut self, quad: usize, uv: FloatRect) {
+        let vertices = &mut self[quad * 4..quad * 4 + 4];
+        vertices[0].tex_coords = Vector2f::new(uv.left, uv.top);
+        vertices[1].tex_coords = Vector2f::new(uv.left + uv.width, uv.top);
+        vertices[2].tex_coords = Vector2f::new(uv.left + uv.width, uv.top + uv.height);
+        vertices[3].tex_coords = Vector2f::new(uv.left, uv.top + uv.height);
+    }
+
+    fn set_quad_color(&mut self, quad: usize, color: Color) {
+        for vertex in self[quad * 4..quad * 4 + 4].iter_mut() {
+            vertex.color = color;
+        }
+    }
 }
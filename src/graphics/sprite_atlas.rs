@@ -3,7 +3,7 @@
 use std::fmt::Display;
 
 use sfml::{
-    graphics::{Drawable, IntRect, Sprite, Texture, Transformable},
+    graphics::{Color, Drawable, IntRect, Sprite, Texture, Transformable},
     system::Vector2f,
 };
 
@@ -34,15 +34,20 @@ pub struct SpriteAtlas<'t> {
 
 impl<'t> SpriteAtlas<'t> {
     /// Crates a new sprite atlas with a given texture and frames as rects in pixel coordinates.
-    pub fn with_texture_and_frames(texture: &'t Texture, frames: &[IntRect]) -> Self {
+    /// Atlases without a texture keep track of their frames but draw nothing visible.
+    pub fn with_texture_and_frames(texture: Option<&'t Texture>, frames: &[IntRect]) -> Self {
+        let mut sprite = Sprite::new();
+        if let Some(texture) = texture {
+            sprite.set_texture(texture, true);
+        }
+        if let Some(first_frame) = frames.first() {
+            sprite.set_texture_rect(*first_frame);
+        }
+
         Self {
             current_frame: 0,
             frames: Vec::from(frames),
-            sprite: if let Some(first_frame) = frames.first() {
-                Sprite::with_texture_and_rect(texture, *first_frame)
-            } else {
-                Sprite::with_texture(texture)
-            },
+            sprite,
         }
     }
 
@@ -50,6 +55,21 @@ impl<'t> SpriteAtlas<'t> {
         self.frames.push(frame);
     }
 
+    /// Replaces every frame of the atlas, keeping the current frame index if possible.
+    pub fn set_frames(&mut self, frames: &[IntRect]) {
+        self.frames = Vec::from(frames);
+        if self.set_frame(self.current_frame).is_err() {
+            self.current_frame = 0;
+            if let Some(first_frame) = frames.first() {
+                self.sprite.set_texture_rect(*first_frame);
+            }
+        }
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
     pub fn current_frame(&self) -> usize {
         self.current_frame
     }
@@ -73,6 +93,12 @@ impl<'t> SpriteAtlas<'t> {
         color.a = alpha;
         self.sprite.set_color(color);
     }
+
+    /// Sets the color the sprite is multiplied by, leaving its alpha untouched.
+    pub fn set_tint(&mut self, tint: Color) {
+        let alpha = self.sprite.color().a;
+        self.sprite.set_color(Color { a: alpha, ..tint });
+    }
 }
 
 impl Transformable for SpriteAtlas<'_> {
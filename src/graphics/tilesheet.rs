@@ -10,11 +10,12 @@ use sfml::{
 };
 
 use thiserror::Error;
-use tiled::{Error, Loader, Tileset};
+use tiled::{Error, Loader, PropertyValue, Tileset};
 
 /// A container for a tileset and the texture it references.
 pub struct Tilesheet {
-    texture: SfBox<Texture>,
+    /// The tileset's texture, or `None` if the tilesheet was loaded without one.
+    texture: Option<SfBox<Texture>>,
     tileset: Arc<Tileset>,
 }
 
@@ -41,7 +42,8 @@ pub enum TilesheetLoadError {
 }
 
 impl Tilesheet {
-    /// Create a tilesheet from a Tiled tileset, loading its texture along the way.
+    /// Create a tilesheet from a Tiled tileset, loading its texture along the way. The texture is
+    /// smoothed unless the tileset is [pixel art](Tilesheet::is_pixel_art).
     pub fn from_tileset(tileset: Arc<Tileset>) -> Result<Self, TilesheetLoadError> {
         let tileset_image = tileset
             .image
@@ -58,10 +60,31 @@ impl Tilesheet {
             )?
         };
 
-        texture.set_smooth(true);
-        texture.generate_mipmap();
+        let mut tilesheet = Tilesheet {
+            texture: None,
+            tileset,
+        };
+        if !tilesheet.is_pixel_art() {
+            texture.set_smooth(true);
+            texture.generate_mipmap();
+        }
+        tilesheet.texture = Some(texture);
 
-        Ok(Tilesheet { texture, tileset })
+        Ok(tilesheet)
+    }
+
+    /// Create a tilesheet from a Tiled tileset without loading its texture, so that it can be used
+    /// where there is no graphics context available, e.g. to play in a terminal. Sprites created
+    /// from it have no texture.
+    pub fn headless_from_tileset(tileset: Arc<Tileset>) -> Result<Self, TilesheetLoadError> {
+        if tileset.image.is_none() {
+            return Err(TilesheetLoadError::InvalidTextureCount);
+        }
+
+        Ok(Tilesheet {
+            texture: None,
+            tileset,
+        })
     }
 
     /// Load a tilesheet from a path to a Tiled tileset, loading its texture along the way.
@@ -71,8 +94,18 @@ impl Tilesheet {
         Self::from_tileset(tileset)
     }
 
-    pub fn texture(&self) -> &Texture {
-        &self.texture
+    /// The tileset's texture, unless the tilesheet is headless.
+    pub fn texture(&self) -> Option<&Texture> {
+        self.texture.as_deref()
+    }
+
+    /// Whether the tileset is pixel art, set via its `pixel_art` property. Pixel art is sampled
+    /// with the nearest texel and is meant to be drawn at integer scales.
+    pub fn is_pixel_art(&self) -> bool {
+        matches!(
+            self.tileset.properties.get("pixel_art"),
+            Some(PropertyValue::BoolValue(true))
+        )
     }
 
     pub fn tileset(&self) -> &Tileset {
@@ -83,7 +116,8 @@ impl Tilesheet {
         let spacing = self.tileset.spacing;
         let tile_width = self.tileset.tile_width;
         let tile_height = self.tileset.tile_height;
-        let tiles_per_row = (self.texture.size().x + spacing) / (tile_width + spacing);
+        let image_width = self.tileset.image.as_ref()?.width as u32;
+        let tiles_per_row = (image_width + spacing) / (tile_width + spacing);
         let x = (id % tiles_per_row) * (tile_width + spacing);
         let y = (id / tiles_per_row) * (tile_height + spacing);
 
@@ -121,7 +155,8 @@ impl Tilesheet {
     }
 
     pub fn tile_sprite(&self, id: u32) -> Option<Sprite> {
+        let texture = self.texture()?;
         self.tile_rect(id)
-            .map(|rect| Sprite::with_texture_and_rect(&self.texture, rect))
+            .map(|rect| Sprite::with_texture_and_rect(texture, rect))
     }
 }
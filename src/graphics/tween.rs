@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+use sfml::system::Vector2f;
+
+/// Smoothly interpolates a position towards a target over a set duration, e.g. to animate objects
+/// moving between cells. Eases out, so that retargeting mid-movement looks natural.
+#[derive(Clone, Copy, Debug)]
+pub struct PositionTween {
+    from: Vector2f,
+    to: Vector2f,
+    elapsed: Duration,
+    duration: Duration,
+    /// Whether the current position is rounded to whole units, e.g. so that pixel art is drawn on
+    /// whole pixels.
+    rounded: bool,
+}
+
+impl PositionTween {
+    /// Creates a tween resting at the given position.
+    pub fn new(position: Vector2f, duration: Duration) -> Self {
+        Self {
+            from: position,
+            to: position,
+            elapsed: duration,
+            duration,
+            rounded: false,
+        }
+    }
+
+    /// Starts moving towards a new target from wherever the tween currently is.
+    pub fn retarget(&mut self, target: Vector2f) {
+        self.from = self.current();
+        self.to = target;
+        self.elapsed = Duration::ZERO;
+    }
+
+    /// Sets how long moving towards a target takes. A duration of zero disables interpolation.
+    pub fn set_duration(&mut self, duration: Duration) {
+        self.duration = duration;
+    }
+
+    /// How long moving towards a target takes.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    pub fn set_rounded(&mut self, rounded: bool) {
+        self.rounded = rounded;
+    }
+
+    pub fn advance(&mut self, delta: Duration) {
+        self.elapsed = self.elapsed.saturating_add(delta);
+    }
+
+    /// The position the tween is currently at.
+    pub fn current(&self) -> Vector2f {
+        if self.is_finished() {
+            return self.to;
+        }
+
+        let t = self.elapsed.as_secs_f32() / self.duration.as_secs_f32();
+        let eased = 1. - (1. - t) * (1. - t);
+        let current = self.from + (self.to - self.from) * eased;
+        if self.rounded {
+            Vector2f::new(current.x.round(), current.y.round())
+        } else {
+            current
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
@@ -1,32 +1,263 @@
-use sfml::{graphics::RenderWindow, window::mouse};
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
 
+use sfml::window::{joystick, mouse, Event, Key};
+
+mod keymap;
+pub use keymap::*;
+
+mod mouse_state;
+pub use mouse_state::*;
+
+/// One of the directional inputs of a joystick.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Stick {
+    Analog,
+    DPad,
+}
+
+/// Keeps track of the player's input.
+///
+/// All of its state is derived from the window events it is given instead of polling input
+/// devices, so that recorded sessions play back exactly like they originally did.
 pub struct InputSystem {
-    clicked_this_frame: bool,
-    clicked_last_frame: bool,
+    has_focus: bool,
+    held_keys: HashSet<Key>,
+    /// The buttons being held, by joystick.
+    held_joystick_buttons: HashSet<(u32, u32)>,
+    joystick_axes: HashMap<(u32, joystick::Axis), f32>,
+    mouse: MouseState,
+    keymap: Keymap,
+    /// The movement action each joystick's stick and d-pad is being held towards, if any.
+    stick_actions: HashMap<(u32, Stick), GameAction>,
+    /// The action triggered by the last joystick movement event handled.
+    last_stick_action: Option<GameAction>,
+    /// The last movement action triggered and the time left until it is repeated.
+    held_movement: Option<(GameAction, Duration)>,
+    /// Movement actions repeated this frame because their inputs were held down.
+    repeated_actions: Vec<GameAction>,
 }
 
 impl InputSystem {
+    /// How far a stick must be pushed, out of 100, before it is considered to point somewhere.
+    const STICK_DEAD_ZONE: f32 = 50.;
+
     pub fn new() -> Self {
+        Self::with_keymap(Keymap::default())
+    }
+
+    pub fn with_keymap(keymap: Keymap) -> Self {
         Self {
-            clicked_this_frame: false,
-            clicked_last_frame: false,
+            has_focus: true,
+            held_keys: HashSet::new(),
+            held_joystick_buttons: HashSet::new(),
+            joystick_axes: HashMap::new(),
+            mouse: MouseState::new(),
+            keymap,
+            stick_actions: HashMap::new(),
+            last_stick_action: None,
+            held_movement: None,
+            repeated_actions: Vec::new(),
         }
     }
 
-    pub fn update(&mut self, window: &RenderWindow) {
-        self.clicked_last_frame = self.clicked_this_frame;
-        self.clicked_this_frame = mouse::Button::Left.is_pressed() && window.has_focus()
+    pub fn update(&mut self, delta: Duration) {
+        self.mouse.update(delta);
+        self.update_held_movement(delta);
+    }
+
+    /// Repeats the movement action being held, if any, according to the keymap's key repeat.
+    fn update_held_movement(&mut self, delta: Duration) {
+        self.repeated_actions.clear();
+
+        let Some((action, mut time_left)) = self.held_movement else {
+            return;
+        };
+
+        if !self.has_focus || !self.is_action_held(action) {
+            self.held_movement = None;
+            return;
+        }
+
+        let interval = self.keymap.repeat.interval.max(Duration::from_millis(1));
+        let mut delta = delta;
+        while delta >= time_left {
+            delta -= time_left;
+            time_left = interval;
+            self.repeated_actions.push(action);
+        }
+        time_left -= delta;
+
+        self.held_movement = Some((action, time_left));
+    }
+
+    /// Updates the input state with an event from the window. Call once per event, before
+    /// handing it to the current state.
+    pub fn handle_event(&mut self, event: &Event) {
+        self.last_stick_action = None;
+        self.mouse.handle_event(event);
+
+        match *event {
+            Event::LostFocus => {
+                // Releases won't be reported while out of focus
+                self.has_focus = false;
+                self.held_keys.clear();
+                self.held_joystick_buttons.clear();
+            }
+            Event::GainedFocus => self.has_focus = true,
+            Event::KeyPressed { code, .. } => {
+                self.held_keys.insert(code);
+            }
+            Event::KeyReleased { code, .. } => {
+                self.held_keys.remove(&code);
+            }
+            Event::JoystickButtonPressed { joystickid, button } => {
+                self.held_joystick_buttons.insert((joystickid, button));
+            }
+            Event::JoystickButtonReleased { joystickid, button } => {
+                self.held_joystick_buttons.remove(&(joystickid, button));
+            }
+            Event::JoystickMoved {
+                joystickid,
+                axis,
+                position,
+            } => {
+                self.joystick_axes.insert((joystickid, axis), position);
+            }
+            Event::JoystickDisconnected { joystickid } => {
+                self.held_joystick_buttons
+                    .retain(|(id, _)| *id != joystickid);
+                self.joystick_axes.retain(|(id, _), _| *id != joystickid);
+                self.stick_actions.retain(|(id, _), _| *id != joystickid);
+            }
+            _ => (),
+        }
+
+        if let Event::JoystickMoved {
+            joystickid, axis, ..
+        } = *event
+        {
+            let stick = match axis {
+                joystick::Axis::X | joystick::Axis::Y => Stick::Analog,
+                joystick::Axis::PovX | joystick::Axis::PovY => Stick::DPad,
+                _ => return,
+            };
+            let axis_position = |axis| {
+                self.joystick_axes
+                    .get(&(joystickid, axis))
+                    .copied()
+                    .unwrap_or_default()
+            };
+            let (x, y) = match stick {
+                Stick::Analog => (
+                    axis_position(joystick::Axis::X),
+                    axis_position(joystick::Axis::Y),
+                ),
+                // The d-pad's vertical axis points upwards, unlike the stick's
+                Stick::DPad => (
+                    axis_position(joystick::Axis::PovX),
+                    -axis_position(joystick::Axis::PovY),
+                ),
+            };
+
+            let action = Self::stick_action(x, y);
+            let previous_action = match action {
+                Some(action) => self.stick_actions.insert((joystickid, stick), action),
+                None => self.stick_actions.remove(&(joystickid, stick)),
+            };
+
+            // Only trigger actions when the stick starts pointing to a new direction
+            if action != previous_action {
+                self.last_stick_action = action;
+            }
+        }
+
+        if let Some(action) = self.action_for_event(event).filter(|a| a.is_movement()) {
+            self.held_movement = Some((action, self.keymap.repeat.initial_delay));
+        }
+    }
+
+    /// Movement actions that should be performed this frame because their inputs are being held
+    /// down. These are generated by the input system itself, so OS key repeats are ignored.
+    pub fn repeated_actions(&self) -> &[GameAction] {
+        &self.repeated_actions
+    }
+
+    /// Obtains the movement action a stick is pointing towards, if it is out of the dead zone.
+    fn stick_action(x: f32, y: f32) -> Option<GameAction> {
+        if x.abs().max(y.abs()) < Self::STICK_DEAD_ZONE {
+            None
+        } else if x.abs() > y.abs() {
+            Some(if x > 0. {
+                GameAction::MoveEast
+            } else {
+                GameAction::MoveWest
+            })
+        } else {
+            Some(if y > 0. {
+                GameAction::MoveSouth
+            } else {
+                GameAction::MoveNorth
+            })
+        }
+    }
+
+    pub fn mouse(&self) -> &MouseState {
+        &self.mouse
+    }
+
+    pub fn mouse_mut(&mut self) -> &mut MouseState {
+        &mut self.mouse
     }
 
     pub fn just_pressed_lmb(&self) -> bool {
-        self.clicked_this_frame && !self.clicked_last_frame
+        self.mouse.just_pressed(mouse::Button::Left)
     }
 
     pub fn is_pressing_lmb(&self) -> bool {
-        self.clicked_this_frame
+        self.mouse.is_held(mouse::Button::Left)
     }
 
     pub fn just_released_lmb(&self) -> bool {
-        !self.clicked_this_frame && self.clicked_last_frame
+        self.mouse.just_released(mouse::Button::Left)
+    }
+
+    /// Translates a window event into the game action it triggers, if any.
+    pub fn action_for_event(&self, event: &Event) -> Option<GameAction> {
+        match *event {
+            Event::KeyPressed { code, ctrl, .. } => self.keymap.action_for_key(code, ctrl),
+            Event::JoystickButtonPressed { button, .. } => self.keymap.action_for_button(button),
+            Event::JoystickMoved { .. } => self.last_stick_action,
+            _ => None,
+        }
+    }
+
+    /// Returns whether any of the keys or joystick buttons bound to the given action is currently
+    /// being held.
+    pub fn is_action_held(&self, action: GameAction) -> bool {
+        let is_ctrl_held =
+            self.held_keys.contains(&Key::LControl) || self.held_keys.contains(&Key::RControl);
+        let is_key_held = self.keymap.bindings(action).iter().any(|binding| {
+            self.held_keys.contains(&binding.key) && (!binding.ctrl || is_ctrl_held)
+        });
+        let is_button_held = self
+            .keymap
+            .button_bindings(action)
+            .iter()
+            .any(|button| self.held_joystick_buttons.iter().any(|(_, b)| b == button));
+        let is_stick_held = self.stick_actions.values().any(|a| *a == action);
+
+        is_key_held || is_button_held || is_stick_held
+    }
+
+    /// Get a reference to the input system's keymap.
+    pub fn keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    pub fn keymap_mut(&mut self) -> &mut Keymap {
+        &mut self.keymap
     }
 }
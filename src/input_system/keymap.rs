@@ -0,0 +1,301 @@
+use std::{collections::BTreeMap, fmt, fs::File, path::PathBuf, time::Duration};
+
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use sfml::window::Key;
+
+/// An action the player can perform, independent of the input used to trigger it.
+///
+/// Actions are ordered as declared, which decides the action triggered by an input bound to
+/// several of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+pub enum GameAction {
+    MoveNorth,
+    MoveSouth,
+    MoveWest,
+    MoveEast,
+    /// Held while moving to pull crates instead of pushing them, in levels that allow it.
+    Pull,
+    Undo,
+    Restart,
+    Back,
+    /// Accepts the currently focused UI element.
+    Confirm,
+    ZoomIn,
+    ZoomOut,
+    /// Held to pan the view of a level around.
+    PanNorth,
+    PanSouth,
+    PanWest,
+    PanEast,
+    /// Undoes any zooming and panning of the view of a level.
+    ResetView,
+    /// Opens the settings screen.
+    OpenSettings,
+    UnlockAllLevels,
+    ResetProgress,
+}
+
+/// A key that triggers a [`GameAction`], optionally requiring Ctrl to be held.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct KeyBinding {
+    pub key: Key,
+    #[serde(default)]
+    pub ctrl: bool,
+}
+
+impl KeyBinding {
+    pub const fn new(key: Key) -> Self {
+        Self { key, ctrl: false }
+    }
+
+    pub const fn with_ctrl(key: Key) -> Self {
+        Self { key, ctrl: true }
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+{:?}", self.key)
+        } else {
+            write!(f, "{:?}", self.key)
+        }
+    }
+}
+
+impl GameAction {
+    /// Returns whether this is one of the actions that move the player around.
+    pub fn is_movement(self) -> bool {
+        matches!(
+            self,
+            GameAction::MoveNorth
+                | GameAction::MoveSouth
+                | GameAction::MoveWest
+                | GameAction::MoveEast
+        )
+    }
+}
+
+/// How movement actions are repeated while their inputs are held down.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct KeyRepeat {
+    /// Time between the first action and the first repeat.
+    pub initial_delay: Duration,
+    /// Time between each repeat after the first one.
+    pub interval: Duration,
+}
+
+impl Default for KeyRepeat {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(250),
+            interval: Duration::from_millis(120),
+        }
+    }
+}
+
+/// Maps keys and joystick buttons to the game actions they trigger. Each action may have multiple
+/// bindings, while inputs bound to several actions trigger the first one of them.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Keymap {
+    bindings: BTreeMap<GameAction, Vec<KeyBinding>>,
+    /// Joystick button indices bound to each action.
+    #[serde(default)]
+    buttons: BTreeMap<GameAction, Vec<u32>>,
+    #[serde(default)]
+    pub repeat: KeyRepeat,
+    /// Whether changes are kept in memory only instead of being written to the config file.
+    #[serde(skip)]
+    in_memory: bool,
+}
+
+impl Keymap {
+    /// Loads the keymap from the config file, using the default bindings for any action it does
+    /// not mention.
+    pub fn from_config_file() -> anyhow::Result<Self> {
+        let mut keymap = ron::de::from_reader::<_, Self>(File::open(Self::config_file_path())?)?;
+
+        let default = Self::default();
+        for (action, bindings) in default.bindings {
+            keymap.bindings.entry(action).or_insert(bindings);
+        }
+        for (action, buttons) in default.buttons {
+            keymap.buttons.entry(action).or_insert(buttons);
+        }
+
+        Ok(keymap)
+    }
+
+    /// Stops writing changes to this keymap to the config file, e.g. while playing back a
+    /// recording.
+    pub fn keep_in_memory(&mut self) {
+        self.in_memory = true;
+    }
+
+    pub fn save(&self) {
+        if self.in_memory {
+            return;
+        }
+
+        crate::context::save_ron_file(self, &Self::config_file_path(), "keymap");
+    }
+
+    /// Makes a key binding the first one of an action, removing it from any other action so that
+    /// it only ever triggers this one.
+    pub fn set_primary_binding(&mut self, action: GameAction, binding: KeyBinding) {
+        for bindings in self.bindings.values_mut() {
+            bindings.retain(|b| *b != binding);
+        }
+
+        let bindings = self.bindings.entry(action).or_default();
+        match bindings.first_mut() {
+            Some(primary) => *primary = binding,
+            None => bindings.push(binding),
+        }
+    }
+
+    /// Makes a joystick button the first one bound to an action, removing it from any other
+    /// action so that it only ever triggers this one.
+    pub fn set_primary_button(&mut self, action: GameAction, button: u32) {
+        for buttons in self.buttons.values_mut() {
+            buttons.retain(|b| *b != button);
+        }
+
+        let buttons = self.buttons.entry(action).or_default();
+        match buttons.first_mut() {
+            Some(primary) => *primary = button,
+            None => buttons.push(button),
+        }
+    }
+
+    /// Sets every key and button binding back to its default, keeping the key repeat as is.
+    pub fn reset_bindings(&mut self) {
+        let default = Self::default();
+        self.bindings = default.bindings;
+        self.buttons = default.buttons;
+    }
+
+    /// Returns the first action bound to the given key and modifier state, if any.
+    pub fn action_for_key(&self, key: Key, ctrl: bool) -> Option<GameAction> {
+        self.bindings.iter().find_map(|(action, bindings)| {
+            bindings
+                .iter()
+                .any(|b| b.key == key && b.ctrl == ctrl)
+                .then_some(*action)
+        })
+    }
+
+    /// Returns the first action bound to the given joystick button, if any.
+    pub fn action_for_button(&self, button: u32) -> Option<GameAction> {
+        self.buttons
+            .iter()
+            .find_map(|(action, buttons)| buttons.contains(&button).then_some(*action))
+    }
+
+    /// The bindings of a given action.
+    pub fn bindings(&self, action: GameAction) -> &[KeyBinding] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// The joystick buttons bound to a given action.
+    pub fn button_bindings(&self, action: GameAction) -> &[u32] {
+        self.buttons.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Lists the keys and joystick buttons bound to an action for showing to the player, joined
+    /// by `separator`.
+    pub fn bindings_text(&self, action: GameAction, separator: &str) -> String {
+        let keys = self.bindings(action).iter().map(KeyBinding::to_string);
+        let buttons = self
+            .button_bindings(action)
+            .iter()
+            .map(|button| format!("Button {}", button));
+        let bindings = keys.chain(buttons).collect::<Vec<_>>();
+
+        if bindings.is_empty() {
+            "None".to_owned()
+        } else {
+            bindings.join(separator)
+        }
+    }
+
+    pub fn config_file_path() -> PathBuf {
+        ProjectDirs::from("", "rusty-pizza", env!("CARGO_PKG_NAME"))
+            .expect("could not obtain project directories")
+            .config_dir()
+            .join("keymap.ron")
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        use GameAction::*;
+
+        let bindings = [
+            (
+                MoveNorth,
+                vec![KeyBinding::new(Key::W), KeyBinding::new(Key::Up)],
+            ),
+            (
+                MoveSouth,
+                vec![KeyBinding::new(Key::S), KeyBinding::new(Key::Down)],
+            ),
+            (
+                MoveWest,
+                vec![KeyBinding::new(Key::A), KeyBinding::new(Key::Left)],
+            ),
+            (
+                MoveEast,
+                vec![KeyBinding::new(Key::D), KeyBinding::new(Key::Right)],
+            ),
+            (
+                Pull,
+                vec![KeyBinding::new(Key::LShift), KeyBinding::new(Key::RShift)],
+            ),
+            (Undo, vec![KeyBinding::new(Key::Q)]),
+            (Restart, vec![KeyBinding::new(Key::R)]),
+            (Back, vec![KeyBinding::new(Key::Escape)]),
+            (
+                Confirm,
+                vec![KeyBinding::new(Key::Enter), KeyBinding::new(Key::Space)],
+            ),
+            (
+                ZoomIn,
+                vec![KeyBinding::new(Key::Add), KeyBinding::new(Key::Equal)],
+            ),
+            (
+                ZoomOut,
+                vec![KeyBinding::new(Key::Subtract), KeyBinding::new(Key::Hyphen)],
+            ),
+            (PanNorth, vec![KeyBinding::with_ctrl(Key::Up)]),
+            (PanSouth, vec![KeyBinding::with_ctrl(Key::Down)]),
+            (PanWest, vec![KeyBinding::with_ctrl(Key::Left)]),
+            (PanEast, vec![KeyBinding::with_ctrl(Key::Right)]),
+            (
+                ResetView,
+                vec![KeyBinding::new(Key::Num0), KeyBinding::new(Key::Numpad0)],
+            ),
+            (OpenSettings, vec![KeyBinding::new(Key::F1)]),
+            (UnlockAllLevels, vec![KeyBinding::with_ctrl(Key::I)]),
+            (ResetProgress, vec![KeyBinding::with_ctrl(Key::N)]),
+        ];
+
+        // Button indices follow the usual layout of XInput gamepads
+        let buttons = [
+            (Confirm, vec![0]),
+            (Back, vec![1, 7]),
+            (Undo, vec![2]),
+            (Restart, vec![3]),
+            (Pull, vec![4, 5]),
+            (OpenSettings, vec![6]),
+        ];
+
+        Self {
+            bindings: bindings.into_iter().collect(),
+            buttons: buttons.into_iter().collect(),
+            repeat: KeyRepeat::default(),
+            in_memory: false,
+        }
+    }
+}
@@ -0,0 +1,253 @@
+use std::{collections::HashMap, mem, time::Duration};
+
+use sfml::{
+    graphics::{FloatRect, Transform},
+    system::{Vector2f, Vector2i},
+    window::{mouse, Event},
+};
+
+/// A mouse drag, from where a button was pressed to where the cursor is or was released.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Drag {
+    pub button: mouse::Button,
+    pub start: Vector2i,
+    pub end: Vector2i,
+}
+
+/// What happened to the mouse during a single frame.
+#[derive(Default)]
+struct MouseFrame {
+    /// Whether the cursor moved.
+    moved: bool,
+    pressed: Vec<mouse::Button>,
+    released: Vec<mouse::Button>,
+    double_clicked: Vec<mouse::Button>,
+    finished_drags: Vec<Drag>,
+    wheel_delta: f32,
+}
+
+/// A button press that hasn't been released yet.
+struct Press {
+    position: Vector2i,
+    /// Whether the cursor has moved far enough from where it was pressed to be dragging.
+    is_dragging: bool,
+}
+
+/// Keeps track of the mouse cursor and buttons.
+///
+/// Like the rest of the input system, frame-based queries such as [`MouseState::just_pressed`]
+/// refer to the events received since the last call to [`MouseState::update`].
+pub struct MouseState {
+    position: Vector2i,
+    is_in_window: bool,
+    presses: HashMap<mouse::Button, Press>,
+    /// The events received since the last update.
+    pending_frame: MouseFrame,
+    frame: MouseFrame,
+    /// The last button clicked, where and when, to detect double clicks.
+    last_click: Option<(mouse::Button, Vector2i, Duration)>,
+    /// Time elapsed since the mouse state was created.
+    time: Duration,
+    ui_transform: Transform,
+    world_transform: Transform,
+}
+
+impl Default for MouseState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MouseState {
+    /// How far the cursor must move while pressing a button, in pixels, for it to start dragging.
+    const DRAG_THRESHOLD: i32 = 4;
+    /// How far apart two clicks can be, in pixels, to count as a double click.
+    const DOUBLE_CLICK_DISTANCE: i32 = 4;
+    const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+    pub fn new() -> Self {
+        Self {
+            position: Vector2i::default(),
+            is_in_window: false,
+            presses: HashMap::new(),
+            pending_frame: MouseFrame::default(),
+            frame: MouseFrame::default(),
+            last_click: None,
+            time: Duration::ZERO,
+            ui_transform: Transform::IDENTITY,
+            world_transform: Transform::IDENTITY,
+        }
+    }
+
+    pub fn update(&mut self, delta: Duration) {
+        self.time += delta;
+        self.frame = mem::take(&mut self.pending_frame);
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        match *event {
+            Event::MouseMoved { x, y } => self.move_to(Vector2i::new(x, y)),
+            Event::MouseEntered => self.is_in_window = true,
+            Event::MouseLeft => self.is_in_window = false,
+            Event::MouseWheelScrolled {
+                wheel: mouse::Wheel::VerticalWheel,
+                delta,
+                ..
+            } => self.pending_frame.wheel_delta += delta,
+            Event::MouseButtonPressed { button, x, y } => {
+                let position = Vector2i::new(x, y);
+                self.move_to(position);
+
+                self.presses.insert(
+                    button,
+                    Press {
+                        position,
+                        is_dragging: false,
+                    },
+                );
+                self.pending_frame.pressed.push(button);
+
+                let is_double_click = self.last_click.is_some_and(|(b, p, time)| {
+                    let distance = position - p;
+                    b == button
+                        && distance.x.abs().max(distance.y.abs()) <= Self::DOUBLE_CLICK_DISTANCE
+                        && self.time - time <= Self::DOUBLE_CLICK_INTERVAL
+                });
+                if is_double_click {
+                    self.pending_frame.double_clicked.push(button);
+                    // A third click starts over instead of being another double click
+                    self.last_click = None;
+                } else {
+                    self.last_click = Some((button, position, self.time));
+                }
+            }
+            Event::MouseButtonReleased { button, x, y } => {
+                self.move_to(Vector2i::new(x, y));
+                self.release(button);
+            }
+            // Releases won't be reported while out of focus, so forget about held buttons without
+            // reporting them as released either
+            Event::LostFocus => self.presses.clear(),
+            _ => (),
+        }
+    }
+
+    fn move_to(&mut self, position: Vector2i) {
+        if position != self.position {
+            self.pending_frame.moved = true;
+        }
+        self.position = position;
+        self.is_in_window = true;
+
+        for press in self.presses.values_mut() {
+            let distance = position - press.position;
+            if distance.x.abs().max(distance.y.abs()) >= Self::DRAG_THRESHOLD {
+                press.is_dragging = true;
+            }
+        }
+    }
+
+    fn release(&mut self, button: mouse::Button) {
+        if let Some(press) = self.presses.remove(&button) {
+            self.pending_frame.released.push(button);
+
+            if press.is_dragging {
+                self.pending_frame.finished_drags.push(Drag {
+                    button,
+                    start: press.position,
+                    end: self.position,
+                });
+            }
+        }
+    }
+
+    /// The position of the cursor in window coordinates.
+    pub fn position(&self) -> Vector2i {
+        self.position
+    }
+
+    pub fn is_in_window(&self) -> bool {
+        self.is_in_window
+    }
+
+    /// Sets the transform used to draw the UI, so that the cursor can be located in UI
+    /// coordinates.
+    pub fn set_ui_transform(&mut self, transform: Transform) {
+        self.ui_transform = transform;
+    }
+
+    /// Sets the transform used to draw the game world, so that the cursor can be located in world
+    /// coordinates.
+    pub fn set_world_transform(&mut self, transform: Transform) {
+        self.world_transform = transform;
+    }
+
+    /// The position of the cursor in UI coordinates.
+    pub fn ui_position(&self) -> Vector2f {
+        Self::untransform(self.ui_transform, self.position)
+    }
+
+    /// The position of the cursor in world coordinates.
+    pub fn world_position(&self) -> Vector2f {
+        Self::untransform(self.world_transform, self.position)
+    }
+
+    fn untransform(transform: Transform, position: Vector2i) -> Vector2f {
+        transform
+            .inverse()
+            .transform_point(Vector2f::new(position.x as f32, position.y as f32))
+    }
+
+    /// Returns whether the cursor is over the given rectangle, in UI coordinates.
+    pub fn is_hovering_ui(&self, rect: FloatRect) -> bool {
+        self.is_in_window && rect.contains(self.ui_position())
+    }
+
+    /// Returns whether the cursor moved this frame.
+    pub fn just_moved(&self) -> bool {
+        self.frame.moved
+    }
+
+    pub fn is_held(&self, button: mouse::Button) -> bool {
+        self.presses.contains_key(&button)
+    }
+
+    pub fn just_pressed(&self, button: mouse::Button) -> bool {
+        self.frame.pressed.contains(&button)
+    }
+
+    pub fn just_released(&self, button: mouse::Button) -> bool {
+        self.frame.released.contains(&button)
+    }
+
+    /// Returns whether the button was pressed twice in a row quickly enough on the same spot.
+    pub fn just_double_clicked(&self, button: mouse::Button) -> bool {
+        self.frame.double_clicked.contains(&button)
+    }
+
+    /// How much the vertical wheel scrolled, positive upwards.
+    pub fn wheel_delta(&self) -> f32 {
+        self.frame.wheel_delta
+    }
+
+    /// The drag being performed with the given button, if any.
+    pub fn drag(&self, button: mouse::Button) -> Option<Drag> {
+        self.presses
+            .get(&button)
+            .filter(|press| press.is_dragging)
+            .map(|press| Drag {
+                button,
+                start: press.position,
+                end: self.position,
+            })
+    }
+
+    /// The drag that the given button just finished by being released, if any.
+    pub fn just_finished_drag(&self, button: mouse::Button) -> Option<Drag> {
+        self.frame
+            .finished_drags
+            .iter()
+            .find(|drag| drag.button == button)
+            .copied()
+    }
+}
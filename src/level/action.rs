@@ -1,14 +1,38 @@
+use std::cmp::Reverse;
+
 use sfml::system::Vector2i;
 
-use super::{tilemap::LevelTile, Direction, Level};
+use super::{objects::CrateStyle, Direction, Level};
+
+/// An entry in a level's undo history.
+#[derive(Clone, Copy)]
+pub enum HistoryEntry {
+    /// An action performed by the player.
+    Action {
+        /// The action that reverts the one performed by the player.
+        undo: Action,
+        /// Whether the player pulled a crate with this action.
+        pulled: bool,
+    },
+    /// A building tile changed with [`Level::set_tile`], along with the id of the tile it had
+    /// before.
+    TileChange {
+        position: Vector2i,
+        previous_id: Option<u32>,
+    },
+}
 
 #[derive(Clone, Copy)]
 pub enum Action {
     /// Pushes a crate forwards and moves the player in the direction given.
     /// The player will look in the direction given.
+    ///
+    /// If `restyle` is set, the crate pushed will be set to that style instead of being painted by
+    /// the tile it lands on.
     Push {
         direction: Direction,
         look_direction: Direction,
+        restyle: Option<CrateStyle>,
     },
 
     /// Pulls or moves backwards in the direction given; e.g. pulling east will move the player to
@@ -16,9 +40,13 @@ pub enum Action {
     /// The player will look in the direction given.
     ///
     /// It will pull out crates from holes!
+    ///
+    /// If `restyle` is set, the crate pulled will be set to that style instead of being painted by
+    /// the tile it lands on.
     Pull {
         direction: Direction,
         look_direction: Direction,
+        restyle: Option<CrateStyle>,
     },
 }
 
@@ -30,6 +58,7 @@ impl Action {
             Action::Push {
                 direction,
                 look_direction,
+                restyle,
             } => {
                 let previous_look_direction = level.player.direction();
                 let movement: Vector2i = direction.into();
@@ -40,35 +69,31 @@ impl Action {
                     let crate_to_move_idx = level
                         .crates
                         .iter()
-                        .enumerate()
-                        .find(|(_idx, c)| c.position() == cell_to_move_to && !c.in_hole())
-                        .map(|(idx, _ref)| idx);
+                        .position(|c| c.occupies(cell_to_move_to) && !c.in_hole());
 
                     if let Some(crate_to_move_idx) = crate_to_move_idx {
-                        let crate_target_position = cell_to_move_to + movement;
+                        let crate_target_position =
+                            level.crates[crate_to_move_idx].position() + movement;
 
-                        let is_crate_movable = !level.is_cell_obstructed(crate_target_position);
+                        let is_crate_movable = level.can_crate_move(crate_to_move_idx, movement);
 
                         if is_crate_movable {
                             // Can move and we are pushing a crate with ourselves
                             level.player.set_transform(cell_to_move_to, look_direction);
+                            level.player.set_pushing(true);
                             level.crates[crate_to_move_idx].set_position(crate_target_position);
 
-                            let target_tile = level.tilemap.get_tile(crate_target_position);
-                            if target_tile == Some(LevelTile::Hole) {
-                                let is_hole_full = level
-                                    .crates
-                                    .iter()
-                                    .any(|c| c.position() == crate_target_position && c.in_hole());
-
-                                if !is_hole_full {
-                                    level.crates[crate_to_move_idx].set_in_hole(true);
-                                }
+                            if let Some(hole_level) = level.hole_level_for_crate(crate_to_move_idx)
+                            {
+                                level.crates[crate_to_move_idx].set_in_hole(Some(hole_level));
                             }
 
+                            let previous_style = level.paint_crate(crate_to_move_idx, restyle);
+
                             Ok(Action::Pull {
                                 direction: direction.inverse(),
                                 look_direction: previous_look_direction,
+                                restyle: Some(previous_style),
                             })
                         } else {
                             // Can't move, something is on the way after the crate
@@ -77,9 +102,11 @@ impl Action {
                     } else {
                         // Can move and no obstacle is on the way
                         level.player.set_transform(cell_to_move_to, look_direction);
+                        level.player.set_pushing(false);
                         Ok(Action::Push {
                             direction: direction.inverse(),
                             look_direction: previous_look_direction,
+                            restyle: None,
                         })
                     }
                 } else {
@@ -90,6 +117,7 @@ impl Action {
             Action::Pull {
                 direction,
                 look_direction,
+                restyle,
             } => {
                 let previous_look_direction = level.player.direction();
                 let movement: Vector2i = direction.into();
@@ -97,40 +125,41 @@ impl Action {
                 let cell_to_pull_from = level.player.position() - movement;
                 let cell_to_move_to = level.player.position() + movement;
 
-                if level.is_cell_walkable(cell_to_move_to) {
+                if level.is_cell_walkable(cell_to_move_to)
+                    && !level.is_cell_obstructed(cell_to_move_to)
+                {
+                    // Prefer crates on top of the ground over those inside of holes, so we
+                    // only pull crates out of holes if there's nothing else to pull, and then
+                    // pull out the topmost one of the stack
                     let crate_to_move_idx = level
                         .crates
                         .iter()
                         .enumerate()
-                        .find(|(_idx, c)| c.position() == cell_to_pull_from)
+                        .filter(|(_idx, c)| c.occupies(cell_to_pull_from))
+                        .min_by_key(|(_idx, c)| (c.in_hole(), Reverse(c.hole_level())))
                         .map(|(idx, _ref)| idx);
 
                     if let Some(crate_to_move_idx) = crate_to_move_idx {
                         // Can move and we are pulling a crate with ourselves
-                        let crate_target_position = level.player.position();
+                        let crate_target_position =
+                            level.crates[crate_to_move_idx].position() + movement;
 
-                        let is_crate_movable = !level.is_cell_obstructed(crate_target_position);
+                        let is_crate_movable = level.can_crate_move(crate_to_move_idx, movement);
 
                         if is_crate_movable {
                             level.player.set_transform(cell_to_move_to, look_direction);
+                            level.player.set_pushing(true);
                             level.crates[crate_to_move_idx].set_position(crate_target_position);
 
-                            let target_tile = level.tilemap.get_tile(crate_target_position);
-                            let is_in_hole = if target_tile == Some(LevelTile::Hole) {
-                                let is_hole_full = level
-                                    .crates
-                                    .iter()
-                                    .any(|c| c.position() == crate_target_position && c.in_hole());
+                            let hole_level = level.hole_level_for_crate(crate_to_move_idx);
+                            level.crates[crate_to_move_idx].set_in_hole(hole_level);
 
-                                !is_hole_full
-                            } else {
-                                false
-                            };
-                            level.crates[crate_to_move_idx].set_in_hole(is_in_hole);
+                            let previous_style = level.paint_crate(crate_to_move_idx, restyle);
 
                             Ok(Action::Push {
                                 direction: direction.inverse(),
                                 look_direction: previous_look_direction,
+                                restyle: Some(previous_style),
                             })
                         } else {
                             // Can't move, something is on the way on the crate target (Should never
@@ -141,9 +170,11 @@ impl Action {
                     } else {
                         // Can move and no obstacle is on the way
                         level.player.set_transform(cell_to_move_to, look_direction);
+                        level.player.set_pushing(false);
                         Ok(Action::Push {
                             direction: direction.inverse(),
                             look_direction: previous_look_direction,
+                            restyle: None,
                         })
                     }
                 } else {
@@ -154,3 +185,87 @@ impl Action {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use sfml::system::Vector2i;
+
+    use tiled::PropertyValue;
+
+    use super::super::{test_utils, Direction, Level};
+
+    #[test]
+    fn pulls_are_counted_and_undone() {
+        let tilesheet = test_utils::tilesheet();
+        let map = test_utils::map(
+            &["######", "#$@  #", "######"],
+            &[("pulling", PropertyValue::BoolValue(true))],
+        );
+        let mut level = Level::from_map_and_tilesheet(&map, &tilesheet).unwrap();
+        assert!(level.pulling_enabled());
+
+        assert!(level.step(Direction::East, true));
+        assert_eq!(level.crates()[0].position(), Vector2i::new(2, 1));
+        assert_eq!(level.player().position(), Vector2i::new(3, 1));
+        assert_eq!((level.action_count(), level.pull_count()), (1, 1));
+
+        // Moving without pulling leaves the crate behind
+        assert!(level.step(Direction::East, false));
+        assert_eq!(level.crates()[0].position(), Vector2i::new(2, 1));
+        assert_eq!((level.action_count(), level.pull_count()), (2, 1));
+
+        assert!(level.undo_step());
+        assert_eq!(level.player().position(), Vector2i::new(3, 1));
+        assert_eq!((level.action_count(), level.pull_count()), (1, 1));
+
+        assert!(level.undo_step());
+        assert_eq!(level.crates()[0].position(), Vector2i::new(1, 1));
+        assert_eq!(level.player().position(), Vector2i::new(2, 1));
+        assert_eq!((level.action_count(), level.pull_count()), (0, 0));
+        assert!(!level.undo_step());
+    }
+
+    #[test]
+    fn pushing_onto_paint_restyles_until_undone() {
+        let tilesheet = test_utils::tilesheet();
+        let map = test_utils::map(&["######", "#@$2 #", "######"], &[]);
+        let mut level = Level::from_map_and_tilesheet(&map, &tilesheet).unwrap();
+
+        assert!(level.step(Direction::East, false));
+        assert_eq!(level.crates()[0].position(), Vector2i::new(3, 1));
+        assert_eq!(level.crates()[0].style().id(), 2);
+
+        // Pushing the crate off the paint keeps the style it was painted
+        assert!(level.step(Direction::East, false));
+        assert_eq!(level.crates()[0].style().id(), 2);
+
+        assert!(level.undo_step());
+        assert_eq!(level.crates()[0].position(), Vector2i::new(3, 1));
+        assert_eq!(level.crates()[0].style().id(), 2);
+
+        assert!(level.undo_step());
+        assert_eq!(level.crates()[0].position(), Vector2i::new(2, 1));
+        assert_eq!(level.crates()[0].style().id(), 1);
+        assert_eq!(level.player().position(), Vector2i::new(1, 1));
+    }
+
+    #[test]
+    fn pulling_onto_paint_restyles_until_undone() {
+        let tilesheet = test_utils::tilesheet();
+        let map = test_utils::map(&["######", "#$2@ #", "######"], &[]);
+        let mut level = Level::from_map_and_tilesheet(&map, &tilesheet).unwrap();
+
+        // Walking over paint has no effect on its own
+        assert!(level.step(Direction::West, false));
+        assert_eq!(level.crates()[0].style().id(), 1);
+
+        assert!(level.step(Direction::East, true));
+        assert_eq!(level.crates()[0].position(), Vector2i::new(2, 1));
+        assert_eq!(level.crates()[0].style().id(), 2);
+
+        assert!(level.undo_step());
+        assert_eq!(level.crates()[0].position(), Vector2i::new(1, 1));
+        assert_eq!(level.crates()[0].style().id(), 1);
+        assert_eq!(level.player().position(), Vector2i::new(2, 1));
+    }
+}
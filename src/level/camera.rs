@@ -0,0 +1,261 @@
+use std::time::Duration;
+
+use sfml::{
+    graphics::{FloatRect, Transform},
+    system::{Vector2f, Vector2u},
+};
+
+use super::camera_transform;
+
+/// A camera for viewing levels. Small levels are fit into the window like [`camera_transform`]
+/// does, while those that would be drawn with tiles smaller than [`Camera::MIN_TILE_SIZE`] are
+/// scrolled around instead, following a focus point such as the player.
+///
+/// On top of that, the view can be zoomed and panned around by the player.
+///
+/// Pixel-perfect cameras only scale levels by whole numbers and place them on whole pixels, so that
+/// pixel art stays crisp, letterboxing whatever space is left.
+#[derive(Clone, Debug)]
+pub struct Camera {
+    /// The size of the map being viewed, in world coordinates.
+    map_size: Vector2f,
+    tile_size: Vector2f,
+    /// Extra vertical space left around the map when fitting it into the window, in world
+    /// coordinates.
+    vertical_padding: f32,
+    /// The world position at the center of the view, once the camera has started following
+    /// something.
+    center: Option<Vector2f>,
+    zoom: f32,
+    /// How far the view has been panned away from where the camera would look, in world
+    /// coordinates.
+    pan: Vector2f,
+    pixel_perfect: bool,
+}
+
+impl Camera {
+    /// The minimum size tiles are drawn at, in pixels, before the camera starts scrolling.
+    pub const MIN_TILE_SIZE: f32 = 48.;
+    /// The fraction of the view, around its center, that the focus can move within without the
+    /// camera following it.
+    const DEAD_ZONE: f32 = 0.3;
+    /// How quickly the camera catches up with its target; higher is snappier.
+    const FOLLOW_SPEED: f32 = 6.;
+    pub const MIN_ZOOM: f32 = 0.25;
+    pub const MAX_ZOOM: f32 = 4.;
+
+    pub fn new(map_size: Vector2f, tile_size: Vector2f, vertical_padding: f32) -> Self {
+        Self {
+            map_size,
+            tile_size,
+            vertical_padding,
+            center: None,
+            zoom: 1.,
+            pan: Vector2f::default(),
+            pixel_perfect: false,
+        }
+    }
+
+    pub fn set_pixel_perfect(&mut self, pixel_perfect: bool) {
+        self.pixel_perfect = pixel_perfect;
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// Sets the zoom, clamped between [`Camera::MIN_ZOOM`] and [`Camera::MAX_ZOOM`].
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+    }
+
+    /// Multiplies the zoom by a factor, keeping the world point under the given window position
+    /// in place.
+    pub fn zoom_at(&mut self, factor: f32, window_size: Vector2u, window_position: Vector2f) {
+        let before = self
+            .transform(window_size)
+            .inverse()
+            .transform_point(window_position);
+        self.set_zoom(self.zoom * factor);
+        let after = self
+            .transform(window_size)
+            .inverse()
+            .transform_point(window_position);
+
+        self.pan += before - after;
+        self.clamp_pan(window_size);
+    }
+
+    /// Pans the view so that the world point that was under a window position is now under
+    /// another one.
+    pub fn pan_by(&mut self, window_size: Vector2u, from: Vector2f, to: Vector2f) {
+        let inverse = self.transform(window_size).inverse();
+        self.pan += inverse.transform_point(from) - inverse.transform_point(to);
+        self.clamp_pan(window_size);
+    }
+
+    /// Limits panning so that the center of the window always shows some part of the map.
+    fn clamp_pan(&mut self, window_size: Vector2u) {
+        let window_center = Vector2f::new(window_size.x as f32, window_size.y as f32) / 2.;
+        let view_center = self
+            .transform(window_size)
+            .inverse()
+            .transform_point(window_center);
+        let clamped = Vector2f::new(
+            view_center.x.clamp(0., self.map_size.x),
+            view_center.y.clamp(0., self.map_size.y),
+        );
+
+        self.pan += clamped - view_center;
+    }
+
+    /// Undoes any zooming and panning.
+    pub fn reset_view(&mut self) {
+        self.zoom = 1.;
+        self.pan = Vector2f::default();
+    }
+
+    /// The scale the map is drawn at on a window of the given size, or `None` if it fits.
+    fn scroll_scale(&self, window_size: Vector2u) -> Option<f32> {
+        let fit_scale = (window_size.x as f32 / self.map_size.x)
+            .min(window_size.y as f32 / (self.map_size.y + self.vertical_padding));
+        let mut min_scale = Self::MIN_TILE_SIZE / self.tile_size.x.min(self.tile_size.y);
+        if self.pixel_perfect {
+            min_scale = min_scale.ceil();
+        }
+
+        (fit_scale < min_scale).then_some(min_scale)
+    }
+
+    /// Moves the camera towards the given focus point, in world coordinates.
+    pub fn update(&mut self, delta: Duration, window_size: Vector2u, focus: Vector2f) {
+        // The window may have been resized since the view was last panned
+        self.clamp_pan(window_size);
+
+        let Some(scale) = self.scroll_scale(window_size) else {
+            self.center = None;
+            return;
+        };
+        let view_size = Vector2f::new(window_size.x as f32, window_size.y as f32) / scale;
+
+        let center = match self.center {
+            Some(center) => {
+                // Only follow the focus once it leaves the dead zone, and just enough to bring it
+                // back to the zone's edge
+                let dead_zone = view_size * (Self::DEAD_ZONE / 2.);
+                let offset = focus - center;
+                let target = center
+                    + Vector2f::new(
+                        offset.x - offset.x.clamp(-dead_zone.x, dead_zone.x),
+                        offset.y - offset.y.clamp(-dead_zone.y, dead_zone.y),
+                    );
+
+                let t = 1. - (-Self::FOLLOW_SPEED * delta.as_secs_f32()).exp();
+                center + (target - center) * t
+            }
+            // Start off centered on the focus
+            None => focus,
+        };
+
+        self.center = Some(self.clamp_center(center, view_size));
+    }
+
+    /// Keeps the view within the map, or centers the map on axes where it is smaller than the
+    /// view.
+    fn clamp_center(&self, center: Vector2f, view_size: Vector2f) -> Vector2f {
+        let clamp_axis = |center: f32, view: f32, map: f32| {
+            if view >= map {
+                map / 2.
+            } else {
+                center.clamp(view / 2., map - view / 2.)
+            }
+        };
+
+        Vector2f::new(
+            clamp_axis(center.x, view_size.x, self.map_size.x),
+            clamp_axis(center.y, view_size.y, self.map_size.y),
+        )
+    }
+
+    /// The transform to draw the map with on a window of the given size.
+    pub fn transform(&self, window_size: Vector2u) -> Transform {
+        let mut transform = Transform::IDENTITY;
+        transform.scale_with_center(
+            self.zoom,
+            self.zoom,
+            window_size.x as f32 / 2.,
+            window_size.y as f32 / 2.,
+        );
+        transform.combine(&self.base_transform(window_size));
+        transform.translate(-self.pan.x, -self.pan.y);
+
+        if self.pixel_perfect {
+            Self::snap_to_pixels(&transform, window_size)
+        } else {
+            transform
+        }
+    }
+
+    /// Rounds the scale of a transform to a whole number, or to the inverse of one when shrinking,
+    /// keeping the same world point at the center of the window and placing it on a whole pixel.
+    fn snap_to_pixels(transform: &Transform, window_size: Vector2u) -> Transform {
+        let window_center = Vector2f::new(window_size.x as f32, window_size.y as f32) / 2.;
+        let world_center = transform.inverse().transform_point(window_center);
+
+        let scale = transform.transform_point(Vector2f::new(1., 0.)).x
+            - transform.transform_point(Vector2f::new(0., 0.)).x;
+        let scale = if scale >= 1. {
+            scale.floor()
+        } else {
+            1. / (1. / scale).ceil()
+        };
+        let offset = window_center - world_center * scale;
+
+        let mut snapped = Transform::IDENTITY;
+        snapped.translate(offset.x.round(), offset.y.round());
+        snapped.scale(scale, scale);
+        snapped
+    }
+
+    /// The area of a window of the given size the map and its padding are drawn in, if the camera
+    /// is pixel-perfect. Anything outside of it is letterboxed.
+    pub fn letterbox(&self, window_size: Vector2u) -> Option<FloatRect> {
+        if !self.pixel_perfect {
+            return None;
+        }
+
+        let transform = self.transform(window_size);
+        let top_left = transform.transform_point(Vector2f::new(0., -self.vertical_padding / 2.));
+        let bottom_right = transform.transform_point(Vector2f::new(
+            self.map_size.x,
+            self.map_size.y + self.vertical_padding / 2.,
+        ));
+        let window = FloatRect::new(0., 0., window_size.x as f32, window_size.y as f32);
+
+        FloatRect::new(
+            top_left.x,
+            top_left.y,
+            bottom_right.x - top_left.x,
+            bottom_right.y - top_left.y,
+        )
+        .intersection(&window)
+    }
+
+    /// The transform to draw the map with before zooming and panning.
+    fn base_transform(&self, window_size: Vector2u) -> Transform {
+        match (self.scroll_scale(window_size), self.center) {
+            (Some(scale), Some(center)) => {
+                let mut transform = Transform::IDENTITY;
+                transform.translate(window_size.x as f32 / 2., window_size.y as f32 / 2.);
+                transform.scale(scale, scale);
+                transform.translate(-center.x, -center.y);
+                transform
+            }
+            _ => camera_transform(
+                window_size,
+                Vector2u::new(self.map_size.x as u32, self.map_size.y as u32),
+                self.vertical_padding,
+            ),
+        }
+    }
+}
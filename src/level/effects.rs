@@ -0,0 +1,97 @@
+//! Cosmetic effects reacting to changes in a level. They never affect the level's logic.
+
+use std::time::Duration;
+
+use sfml::{
+    graphics::Color,
+    system::{Vector2f, Vector2i},
+};
+
+use crate::graphics::ParticleBurst;
+
+use super::Level;
+
+/// Dust raised by a crate dropping into a hole.
+const DROP_DUST: ParticleBurst = ParticleBurst {
+    count: 12,
+    colors: &[
+        Color::rgb(0xc8, 0xb4, 0x96),
+        Color::rgb(0xa0, 0x8c, 0x78),
+        Color::rgb(0xe6, 0xdc, 0xc8),
+    ],
+    speed: (40., 140.),
+    lifetime: Duration::from_millis(450),
+    size: 14.,
+    gravity: -60.,
+};
+
+/// Sparkles shown when a goal gets filled.
+const GOAL_SPARKLE: ParticleBurst = ParticleBurst {
+    count: 16,
+    colors: &[
+        Color::rgb(0xff, 0xe0, 0x60),
+        Color::rgb(0xff, 0xff, 0xc0),
+        Color::WHITE,
+    ],
+    speed: (80., 240.),
+    lifetime: Duration::from_millis(600),
+    size: 10.,
+    gravity: 0.,
+};
+
+/// Confetti shot out of every goal when the level is won.
+const WIN_CONFETTI: ParticleBurst = ParticleBurst {
+    count: 40,
+    colors: &[
+        Color::rgb(0xe7, 0x4c, 0x3c),
+        Color::rgb(0x34, 0x98, 0xdb),
+        Color::rgb(0x2e, 0xcc, 0x71),
+        Color::rgb(0xf1, 0xc4, 0x0f),
+        Color::rgb(0x9b, 0x59, 0xb6),
+    ],
+    speed: (250., 600.),
+    lifetime: Duration::from_millis(1400),
+    size: 16.,
+    gravity: 700.,
+};
+
+impl Level<'_> {
+    /// Spawns effects for whatever changed since the last time this was called.
+    pub(super) fn spawn_effects(&mut self) {
+        for (idx, c) in self.crates.iter().enumerate() {
+            let was_in_hole = self.crates_seen_in_hole.get(idx).copied().unwrap_or(false);
+            if c.in_hole() && !was_in_hole {
+                for cell in c.cells() {
+                    self.effects
+                        .emit(Self::cell_center(cell, self.grid_size), &DROP_DUST);
+                }
+            }
+        }
+        self.crates_seen_in_hole = self.crates.iter().map(|c| c.in_hole()).collect();
+
+        for (goal, was_done) in self.goals.iter().zip(self.goals_seen_done.iter()) {
+            if goal.is_done() && !was_done {
+                self.effects.emit(
+                    Self::cell_center(goal.position(), self.grid_size),
+                    &GOAL_SPARKLE,
+                );
+            }
+        }
+        self.goals_seen_done = self.goals.iter().map(|g| g.is_done()).collect();
+
+        let is_won = self.is_won();
+        if is_won && !self.was_won {
+            for goal in self.goals.iter() {
+                self.effects.emit(
+                    Self::cell_center(goal.position(), self.grid_size),
+                    &WIN_CONFETTI,
+                );
+            }
+        }
+        self.was_won = is_won;
+    }
+
+    fn cell_center(cell: Vector2i, grid_size: Vector2f) -> Vector2f {
+        (Vector2f::new(cell.x as f32, cell.y as f32) + Vector2f::new(0.5, 0.5)).cwise_mul(grid_size)
+    }
+}
@@ -26,6 +26,15 @@ pub enum LevelLoadError {
     InvalidObjectGroups,
     #[error("Invalid object: {0:?}")]
     InvalidObject(tiled::ObjectData),
+    #[error(
+        "Invalid crate shape: The `shape` of crate object {id} ({name:?}) is invalid: {source}"
+    )]
+    InvalidCrateShape {
+        id: u32,
+        name: String,
+        #[source]
+        source: CrateShapeError,
+    },
     #[error("Tiled error: {0}")]
     TiledError(
         #[from]
@@ -33,3 +42,16 @@ pub enum LevelLoadError {
         tiled::Error,
     ),
 }
+
+/// Represents an error in the `shape` property of a crate.
+#[derive(Debug, Error)]
+pub enum CrateShapeError {
+    #[error("unexpected character {0:?}, only `#` and `.` are allowed")]
+    InvalidCharacter(char),
+    #[error("row {0} is empty")]
+    EmptyRow(usize),
+    #[error("it has no occupied cells")]
+    NoCells,
+    #[error("its occupied cells are not all connected to each other")]
+    Disconnected,
+}
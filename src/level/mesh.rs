@@ -0,0 +1,169 @@
+//! The mesh of a level's static tiles, which can be changed one tile at a time.
+
+use std::time::Duration;
+
+use sfml::{
+    graphics::{Color, Vertex},
+    system::{Vector2f, Vector2u},
+};
+use tiled::LayerTileData;
+
+use crate::graphics::{FrameAnimation, QuadMeshable, Tilesheet};
+
+/// The quads a level's floor and building tiles are drawn with.
+#[derive(Clone)]
+pub(super) struct LevelMesh {
+    pub vertices: Vec<Vertex>,
+    size_in_tiles: Vector2u,
+    grid_size: Vector2f,
+    /// How much tiles are grown on each side, in tiles, to hide seams between them when scaled.
+    /// Pixel art is drawn at integer scales, so it doesn't need it.
+    tile_dilation: f32,
+    /// The id of the building tile of each cell, if it has one.
+    building_tiles: Vec<Option<u32>>,
+    /// The quad showing the building tile of each cell, if it has ever had one.
+    building_quads: Vec<Option<usize>>,
+    /// The quads showing tiles that are animated in Tiled, along with their animation, whose
+    /// frames are tile ids.
+    animated_quads: Vec<(usize, FrameAnimation)>,
+    /// How long tile animations have been playing for.
+    animation_time: Duration,
+}
+
+impl LevelMesh {
+    const FLOOR_OFFSET: Vector2f = Vector2f::new(0.5f32, 0.5f32);
+    const TILE_DILATION: f32 = 0.01;
+
+    /// Generates the mesh of a level out of its building and floor layers.
+    pub fn new(
+        size_in_tiles: Vector2u,
+        building_layer: &[Option<LayerTileData>],
+        floor_layer: &[Option<LayerTileData>],
+        tilesheet: &Tilesheet,
+        grid_size: Vector2f,
+    ) -> Self {
+        let mut mesh = Self {
+            vertices: Vec::new(),
+            size_in_tiles,
+            grid_size,
+            tile_dilation: if tilesheet.is_pixel_art() {
+                0.
+            } else {
+                Self::TILE_DILATION
+            },
+            building_tiles: building_layer
+                .iter()
+                .map(|tile| tile.as_ref().map(|t| t.id()))
+                .collect(),
+            building_quads: vec![None; building_layer.len()],
+            animated_quads: Vec::new(),
+            animation_time: Duration::ZERO,
+        };
+
+        let iter = building_layer.iter().zip(floor_layer.iter()).enumerate();
+        for (i, (b_tile, f_tile)) in iter {
+            let position = mesh.cell_position(i);
+            if let Some(f_tile) = f_tile {
+                mesh.add_tile_quad(position + Self::FLOOR_OFFSET, f_tile.id(), tilesheet)
+                    .expect("obtaining floor tile UV");
+            }
+            if let Some(b_tile) = b_tile {
+                let quad = mesh
+                    .add_tile_quad(position, b_tile.id(), tilesheet)
+                    .expect("obtaining building tile UV");
+                mesh.building_quads[i] = Some(quad);
+            }
+        }
+
+        mesh
+    }
+
+    /// The position of the cell with the given index, in tiles.
+    fn cell_position(&self, cell: usize) -> Vector2f {
+        Vector2f::new(
+            (cell % self.size_in_tiles.x as usize) as f32,
+            (cell / self.size_in_tiles.x as usize) as f32,
+        )
+    }
+
+    /// Adds a quad showing a tile at the given position in tiles, returning the quad's index.
+    fn add_tile_quad(
+        &mut self,
+        position: Vector2f,
+        id: u32,
+        tilesheet: &Tilesheet,
+    ) -> Option<usize> {
+        let dilation = Vector2f::new(self.tile_dilation, self.tile_dilation);
+        self.vertices.add_quad(
+            (position - dilation).cwise_mul(self.grid_size),
+            self.grid_size * (1f32 + self.tile_dilation * 2.),
+            tilesheet.tile_uv(id)?,
+        );
+
+        let quad = self.vertices.len() / 4 - 1;
+        self.set_quad_animation(quad, Some(id), tilesheet);
+        Some(quad)
+    }
+
+    /// Makes a quad play the Tiled animation of the given tile, or stops it from being animated if
+    /// the tile has none.
+    fn set_quad_animation(&mut self, quad: usize, id: Option<u32>, tilesheet: &Tilesheet) {
+        self.animated_quads
+            .retain(|(animated, _)| *animated != quad);
+
+        let Some(id) = id else {
+            return;
+        };
+        let animation = FrameAnimation::from_tile_ids(tilesheet, id);
+        if animation.is_animated() {
+            self.animated_quads.push((quad, animation));
+        }
+    }
+
+    /// Shows the tile with the given id as the building tile of the cell with the given index, or
+    /// no building tile at all if `None`. Returns the id of the tile shown before.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tile id is not in the tilesheet.
+    pub fn set_building_tile(
+        &mut self,
+        cell: usize,
+        id: Option<u32>,
+        tilesheet: &Tilesheet,
+    ) -> Option<u32> {
+        match (self.building_quads[cell], id) {
+            (Some(quad), Some(id)) => {
+                let uv = tilesheet.tile_uv(id).expect("obtaining building tile UV");
+                self.vertices.set_quad_uv(quad, uv);
+                self.vertices.set_quad_color(quad, Color::WHITE);
+            }
+            (Some(quad), None) => self.vertices.set_quad_color(quad, Color::TRANSPARENT),
+            (None, Some(id)) => {
+                let quad = self
+                    .add_tile_quad(self.cell_position(cell), id, tilesheet)
+                    .expect("obtaining building tile UV");
+                self.building_quads[cell] = Some(quad);
+            }
+            (None, None) => (),
+        }
+
+        if let Some(quad) = self.building_quads[cell] {
+            self.set_quad_animation(quad, id, tilesheet);
+        }
+
+        std::mem::replace(&mut self.building_tiles[cell], id)
+    }
+
+    /// Shows the current frame of every animated tile.
+    pub fn update(&mut self, delta: Duration, tilesheet: &Tilesheet) {
+        self.animation_time += delta;
+
+        for (quad, animation) in self.animated_quads.iter() {
+            let tile = animation.frame_at(self.animation_time) as u32;
+            if let Some(uv) = tilesheet.tile_uv(tile) {
+                self.vertices.set_quad_uv(*quad, uv);
+            }
+        }
+    }
+}
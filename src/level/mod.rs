@@ -4,30 +4,43 @@
 
 mod action;
 use action::*;
+mod camera;
+mod effects;
 mod error;
+mod mesh;
 pub mod objects;
+mod pathfinding;
 mod player;
+#[cfg(test)]
+mod test_utils;
+mod thumbnail;
 pub mod tilemap;
 
+use std::{collections::VecDeque, time::Duration};
+
 use rand::{prelude::SliceRandom, thread_rng};
 use sfml::{
     audio::{Sound, SoundSource},
-    graphics::{Color, Drawable, PrimitiveType, Transform, Vertex},
+    graphics::{Color, Drawable, PrimitiveType, RectangleShape, Shape, Transform, Transformable},
     system::{Vector2f, Vector2i, Vector2u},
-    window::{Event, Key},
+    window::Event,
 };
-use tiled::{LayerTileData, Map};
+use tiled::{LayerTileData, Map, PropertyValue};
 
 use crate::{
     context::Context,
-    graphics::{QuadMeshable, Tilesheet},
+    graphics::{ParticleSystem, Tilesheet},
+    input_system::GameAction,
     ui::{get_ui_obj_from_tiled_obj, UiObject},
 };
 
-pub use self::error::LevelLoadError;
+pub use self::camera::Camera;
+pub use self::error::{CrateShapeError, LevelLoadError};
+use self::mesh::LevelMesh;
+pub use self::pathfinding::CratePushPlans;
 pub use self::player::Player;
 use self::{
-    objects::{Crate, Goal},
+    objects::{Crate, CrateStyle, Goal},
     tilemap::{LevelTile, Tilemap},
 };
 
@@ -42,6 +55,13 @@ pub enum Direction {
 }
 
 impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::South,
+        Direction::West,
+        Direction::East,
+    ];
+
     pub fn inverse(self) -> Self {
         match self {
             Direction::North => Direction::South,
@@ -71,7 +91,7 @@ fn play_move_sound(context: &mut Context) {
         .expect("No walk sounds to play");
 
     let mut sound = Sound::with_buffer(buf_to_use);
-    sound.set_volume(40.);
+    sound.set_volume(context.config.sound_volume);
     sound.play();
     context.sound.add_sound(sound);
 }
@@ -84,7 +104,7 @@ fn play_undo_sound(context: &mut Context) {
         .expect("No walk sounds to play");
 
     let mut sound = Sound::with_buffer(buf_to_use);
-    sound.set_volume(40.);
+    sound.set_volume(context.config.sound_volume);
     sound.play();
     context.sound.add_sound(sound);
 }
@@ -97,28 +117,75 @@ pub struct Level<'s> {
     goals: Vec<Goal<'s>>,
     tilemap: Tilemap,
     tilesheet: &'s Tilesheet,
-    vertices: Vec<Vertex>,
+    mesh: LevelMesh,
     pub background_color: Color,
     player: Player<'s>,
-    undo_history: Vec<Action>,
+    undo_history: Vec<HistoryEntry>,
+    pulling_enabled: bool,
+    par_moves: Option<usize>,
+    max_moves: Option<usize>,
+    grid_size: Vector2f,
+    /// Moves the player will perform automatically, one after another, e.g. to walk to a cell.
+    queued_moves: VecDeque<Direction>,
+    time_until_next_queued_move: Duration,
+    /// A move input while the player was still being drawn moving, along with whether it was a
+    /// pull. It is performed once the player arrives, so that quick inputs are all seen played
+    /// out.
+    buffered_move: Option<(Direction, bool)>,
+    /// The crate selected by clicking on it, along with where it can be pushed to and which of its
+    /// cells was clicked relative to its position.
+    crate_selection: Option<(CratePushPlans, Vector2i)>,
+    /// Purely cosmetic particle effects.
+    effects: ParticleSystem,
+    /// Which crates were in a hole the last time effects were spawned.
+    crates_seen_in_hole: Vec<bool>,
+    /// Which goals were done the last time effects were spawned.
+    goals_seen_done: Vec<bool>,
+    /// Whether the level was won the last time effects were spawned.
+    was_won: bool,
 }
 
 /// Constructors & parsing-related functions
 impl<'s> Level<'s> {
-    /// Load a sokoban level from a Tiled map and its tilesheet.
+    /// How long the player and crates take to be drawn moving from a cell to another by default.
+    pub const DEFAULT_MOVE_DURATION: Duration = Duration::from_millis(100);
+
+    /// Load a sokoban level from a Tiled map, using the active theme's tilesheet and colors and the
+    /// configured animation speed.
     pub fn from_map(map: &Map, ctx: &Context<'s>) -> Result<Level<'s>, LevelLoadError> {
+        let mut level = Self::from_map_and_tilesheet(map, ctx.tilesheet())?;
+        level.background_color = ctx.background_color(level.background_color);
+        level.set_pixel_snapping(ctx.pixel_perfect(ctx.tilesheet()));
+        level.overlay = map
+            .layers()
+            .find(|o| o.name == "overlay")
+            .map_or(vec![], |o| {
+                o.as_object_layer()
+                    .unwrap()
+                    .objects()
+                    .map(|object| get_ui_obj_from_tiled_obj(ctx, &object).unwrap())
+                    .collect()
+            });
+
+        Ok(level)
+    }
+
+    /// Load a sokoban level from a Tiled map and a tilesheet, without its UI overlay. Does not need
+    /// a [`Context`], so levels can be played outside of the game's window.
+    pub fn from_map_and_tilesheet(
+        map: &Map,
+        tilesheet: &'s Tilesheet,
+    ) -> Result<Level<'s>, LevelLoadError> {
         if map.infinite() {
             return Err(LevelLoadError::NotFinite);
         }
 
-        let assets = ctx.assets;
-
         let size = Vector2u::new(map.width, map.height);
 
         let (building_layer, floor_layer) =
             Self::get_building_and_floor_layers(map).ok_or(LevelLoadError::InvalidLayers)?;
 
-        let tilemap = Tilemap::from_tiled_layer(size, &building_layer, assets.tilesheet.tileset());
+        let tilemap = Tilemap::from_tiled_layer(size, &building_layer, tilesheet.tileset());
 
         let object_group = map
             .layers()
@@ -134,12 +201,10 @@ impl<'s> Level<'s> {
             for object in object_group.objects() {
                 use objects::parsing::MapObject::{self, *};
 
-                match MapObject::from_tiled_object(&object, map, &assets.tilesheet) {
-                    Some(Spawn { position }) => player_spawn = Some(position),
-                    Some(Crate(c)) => crates.push(c),
-                    Some(Goal(g)) => goals.push(g),
-
-                    None => return Err(LevelLoadError::InvalidObject((*object).clone())),
+                match MapObject::from_tiled_object(&object, map, tilesheet)? {
+                    Spawn { position } => player_spawn = Some(position),
+                    Crate(c) => crates.push(c),
+                    Goal(g) => goals.push(g),
                 }
             }
 
@@ -155,45 +220,62 @@ impl<'s> Level<'s> {
         };
 
         let grid_size = Vector2f::new(map.tile_width as f32, map.tile_height as f32);
-        let player =
-            Player::new(player_spawn, &assets.tilesheet, grid_size).expect("constructing player");
+        let player = Player::new(player_spawn, tilesheet, grid_size).expect("constructing player");
 
         let background_color = map
             .background_color
             .map(|c| Color::rgb(c.red, c.green, c.blue))
             .unwrap_or(Color::BLACK);
 
-        let vertices = Self::generate_vertices(
-            &size,
-            &building_layer,
-            &floor_layer,
-            &assets.tilesheet,
-            grid_size,
+        let pulling_enabled = matches!(
+            map.properties.get("pulling"),
+            Some(PropertyValue::BoolValue(true))
         );
 
-        let overlay = map
-            .layers()
-            .find(|o| o.name == "overlay")
-            .map_or(vec![], |o| {
-                o.as_object_layer()
-                    .unwrap()
-                    .objects()
-                    .map(|object| get_ui_obj_from_tiled_obj(ctx, &object).unwrap())
-                    .collect()
-            });
+        let get_move_count_property = |name: &str| match map.properties.get(name) {
+            Some(PropertyValue::IntValue(moves)) if *moves >= 0 => Some(*moves as usize),
+            Some(_) => {
+                log::warn!("level has an invalid `{}` property", name);
+                None
+            }
+            None => None,
+        };
+        let par_moves = get_move_count_property("par_moves");
+        let max_moves = get_move_count_property("max_moves");
+
+        let mesh = LevelMesh::new(size, &building_layer, &floor_layer, tilesheet, grid_size);
 
-        Ok(Self {
-            overlay,
+        let mut level = Self {
+            overlay: Vec::new(),
             player_spawn,
             crates,
             goals,
-            vertices,
+            mesh,
             tilemap,
-            tilesheet: &assets.tilesheet,
+            tilesheet,
             background_color,
             player,
             undo_history: vec![],
-        })
+            pulling_enabled,
+            par_moves,
+            max_moves,
+            grid_size,
+            queued_moves: VecDeque::new(),
+            time_until_next_queued_move: Duration::ZERO,
+            buffered_move: None,
+            crate_selection: None,
+            effects: ParticleSystem::new(),
+            crates_seen_in_hole: Vec::new(),
+            goals_seen_done: Vec::new(),
+            was_won: false,
+        };
+        level.set_move_duration(Self::DEFAULT_MOVE_DURATION);
+        // Start off with goals filled by crates already, so they don't spawn effects
+        level.update_crate_opacity();
+        level.goals_seen_done = level.goals.iter().map(|g| g.is_done()).collect();
+        level.was_won = level.is_won();
+
+        Ok(level)
     }
 
     /// Extracts the building and floor layers from the given Tiled ones.
@@ -221,49 +303,6 @@ impl<'s> Level<'s> {
         Some((building_tiles, floor_tiles))
     }
 
-    /// Generates a static level mesh and returns it.
-    fn generate_vertices(
-        size_in_tiles: &Vector2u,
-        building_layer: &[Option<LayerTileData>],
-        floor_layer: &[Option<LayerTileData>],
-        tilesheet: &Tilesheet,
-        grid_size: Vector2f,
-    ) -> Vec<Vertex> {
-        const FLOOR_OFFSET: Vector2f = Vector2f::new(0.5f32, 0.5f32);
-        const TILE_DILATION: f32 = 0.01;
-
-        let mut vertices = Vec::new();
-
-        let iter = building_layer.iter().zip(floor_layer.iter()).enumerate();
-        for (i, (b_tile, f_tile)) in iter {
-            let position = Vector2f::new(
-                (i % size_in_tiles.x as usize) as f32,
-                (i / size_in_tiles.x as usize) as f32,
-            );
-            if let Some(f_tile) = f_tile {
-                vertices.add_quad(
-                    (position + FLOOR_OFFSET - Vector2f::new(TILE_DILATION, TILE_DILATION))
-                        .cwise_mul(grid_size),
-                    grid_size * (1f32 + TILE_DILATION * 2.),
-                    tilesheet
-                        .tile_uv(f_tile.id())
-                        .expect("obtaining floor tile UV"),
-                );
-            }
-            if let Some(b_tile) = b_tile {
-                vertices.add_quad(
-                    (position - Vector2f::new(TILE_DILATION, TILE_DILATION)).cwise_mul(grid_size),
-                    grid_size * (1f32 + TILE_DILATION * 2.),
-                    tilesheet
-                        .tile_uv(b_tile.id())
-                        .expect("obtaining building tile UV"),
-                );
-            }
-        }
-
-        vertices
-    }
-
     pub fn tilesheet(&self) -> &Tilesheet {
         self.tilesheet
     }
@@ -272,7 +311,28 @@ impl<'s> Level<'s> {
 /// Public instance functions
 impl Level<'_> {
     pub fn action_count(&self) -> usize {
-        self.undo_history.len()
+        self.undo_history
+            .iter()
+            .filter(|e| matches!(e, HistoryEntry::Action { .. }))
+            .count()
+    }
+
+    /// How many of the actions performed by the player were pulls.
+    pub fn pull_count(&self) -> usize {
+        self.undo_history
+            .iter()
+            .filter(|e| matches!(e, HistoryEntry::Action { pulled: true, .. }))
+            .count()
+    }
+
+    /// Whether the player can pull crates in this level, set via the `pulling` map property.
+    pub fn pulling_enabled(&self) -> bool {
+        self.pulling_enabled
+    }
+
+    /// The center of the player as it is being drawn, in world coordinates.
+    pub fn player_focus(&self) -> Vector2f {
+        self.player.draw_position() + self.grid_size / 2.
     }
 
     /// The tilemap associated to the level.
@@ -280,51 +340,278 @@ impl Level<'_> {
         &self.tilemap
     }
 
+    pub fn crates(&self) -> &[Crate<'_>] {
+        &self.crates
+    }
+
+    pub fn goals(&self) -> &[Goal<'_>] {
+        &self.goals
+    }
+
+    pub fn player(&self) -> &Player<'_> {
+        &self.player
+    }
+
     /// Returns whether all the crates are in goals or not.
     pub fn is_won(&self) -> bool {
         self.goals.iter().all(|g| g.is_done())
     }
 
+    /// The amount of moves the level is expected to be solved in, set via the `par_moves` map
+    /// property.
+    pub fn par_moves(&self) -> Option<usize> {
+        self.par_moves
+    }
+
+    /// The maximum amount of moves the player may use, set via the `max_moves` map property.
+    pub fn max_moves(&self) -> Option<usize> {
+        self.max_moves
+    }
+
+    /// How many more moves the player may use before failing, if the level has a limit.
+    pub fn remaining_moves(&self) -> Option<usize> {
+        self.max_moves
+            .map(|max_moves| max_moves.saturating_sub(self.action_count()))
+    }
+
+    /// Returns whether the player has used more moves than allowed by the level.
+    pub fn is_failed(&self) -> bool {
+        self.max_moves
+            .is_some_and(|max_moves| self.action_count() > max_moves)
+    }
+
+    /// Rates the player's solution from one to three stars depending on how close it is to par,
+    /// if the level has one.
+    pub fn star_rating(&self) -> Option<u8> {
+        let par_moves = self.par_moves?;
+        let moves = self.action_count();
+
+        Some(if moves <= par_moves {
+            3
+        } else if moves <= par_moves + par_moves / 2 {
+            2
+        } else {
+            1
+        })
+    }
+
     pub fn handle_event(&mut self, context: &mut Context, event: Event) {
-        match event {
-            Event::KeyPressed { code: Key::A, .. }
-            | Event::KeyPressed {
-                code: Key::Left, ..
-            } => {
-                self.move_player(Direction::West, context);
-            }
-            Event::KeyPressed { code: Key::W, .. } | Event::KeyPressed { code: Key::Up, .. } => {
-                self.move_player(Direction::North, context);
-            }
-            Event::KeyPressed { code: Key::S, .. }
-            | Event::KeyPressed {
-                code: Key::Down, ..
-            } => {
-                self.move_player(Direction::South, context);
-            }
-            Event::KeyPressed { code: Key::D, .. }
-            | Event::KeyPressed {
-                code: Key::Right, ..
-            } => {
-                self.move_player(Direction::East, context);
-            }
-            Event::KeyPressed { code: Key::Q, .. } => {
+        if let Some(action) = context.input.action_for_event(&event) {
+            self.handle_action(context, action);
+        }
+    }
+
+    /// Performs a game action, such as moving the player around or undoing.
+    pub fn handle_action(&mut self, context: &mut Context, action: GameAction) {
+        let direction = match action {
+            GameAction::MoveWest => Direction::West,
+            GameAction::MoveNorth => Direction::North,
+            GameAction::MoveSouth => Direction::South,
+            GameAction::MoveEast => Direction::East,
+            GameAction::Undo => {
+                self.cancel_pending_moves();
                 self.undo(context);
+                return;
             }
-            _ => (),
+            _ => return,
+        };
+
+        // Manual movement takes over any walk in progress
+        self.cancel_pending_moves();
+
+        // Only undoing is allowed after running out of moves
+        if self.is_failed() {
+            return;
+        }
+
+        let pull = self.pulling_enabled && context.input.is_action_held(GameAction::Pull);
+        if self.player.is_moving() {
+            self.buffered_move = Some((direction, pull));
+        } else if pull {
+            self.pull_player(direction, context);
+        } else {
+            self.move_player(direction, context);
         }
     }
 
+    /// Forgets about any walk in progress, buffered move and crate selection.
+    fn cancel_pending_moves(&mut self) {
+        self.queued_moves.clear();
+        self.buffered_move = None;
+        self.crate_selection = None;
+    }
+
     pub fn undo(&mut self, context: &mut Context) {
-        if let Some(m) = self.undo_history.pop() {
-            m.apply(self).expect("couldn't undo move");
+        if self.undo_step() {
             play_undo_sound(context);
         }
     }
 
+    /// Reverts the last action performed, along with any tile changes made after it, returning
+    /// whether there was anything to revert. Unlike [`Level::undo`], this does not play any sounds.
+    pub fn undo_step(&mut self) -> bool {
+        let mut reverted = false;
+        while let Some(entry) = self.undo_history.pop() {
+            reverted = true;
+            match entry {
+                HistoryEntry::Action { undo, .. } => {
+                    undo.apply(self).expect("couldn't undo move");
+                    break;
+                }
+                HistoryEntry::TileChange {
+                    position,
+                    previous_id,
+                } => {
+                    self.replace_tile(position, previous_id)
+                        .expect("couldn't undo tile change");
+                }
+            }
+        }
+        self.update_crate_opacity();
+
+        reverted
+    }
+
     /// Updates the level and the objects within it. Call every frame.
-    pub fn update(&mut self, _context: &mut Context, _delta: std::time::Duration) {
+    ///
+    /// Animations play at the speed set in the config, while queued moves are performed at the
+    /// same pace regardless.
+    pub fn update(&mut self, context: &mut Context, delta: Duration) {
+        self.update_queued_moves(context, delta);
         self.update_crate_opacity();
+
+        let animation_delta = delta.mul_f32(context.animation_speed());
+        self.spawn_effects();
+        self.effects.update(animation_delta);
+        self.mesh.update(animation_delta, self.tilesheet);
+
+        self.player.update(animation_delta);
+        for c in self.crates.iter_mut() {
+            c.update(animation_delta);
+        }
+
+        self.update_buffered_move(context);
+    }
+
+    /// Sets how long the player and crates take to be drawn moving from a cell to another. Their
+    /// positions change right away regardless, so the level stays responsive to input.
+    pub fn set_move_duration(&mut self, duration: Duration) {
+        self.player.set_move_duration(duration);
+        for c in self.crates.iter_mut() {
+            c.set_move_duration(duration);
+        }
+    }
+
+    /// Sets whether the player and crates are drawn on whole world units while they move, which
+    /// are whole pixels when the level is drawn pixel-perfect.
+    pub fn set_pixel_snapping(&mut self, snap: bool) {
+        self.player.set_pixel_snapping(snap);
+        for c in self.crates.iter_mut() {
+            c.set_pixel_snapping(snap);
+        }
+    }
+
+    /// Performs the buffered move once the player is done being drawn moving.
+    fn update_buffered_move(&mut self, context: &mut Context) {
+        if self.player.is_moving() {
+            return;
+        }
+
+        match self.buffered_move.take() {
+            Some(_) if self.is_failed() => (),
+            Some((direction, true)) => self.pull_player(direction, context),
+            Some((direction, false)) => self.move_player(direction, context),
+            None => (),
+        }
+    }
+
+    /// Performs the next queued move once enough time has passed since the last one.
+    fn update_queued_moves(&mut self, context: &mut Context, delta: Duration) {
+        const QUEUED_MOVE_INTERVAL: Duration = Duration::from_millis(80);
+
+        if self.queued_moves.is_empty() {
+            return;
+        }
+        // Queued moves are planned within the move limit, but stop for good if it is exceeded
+        // regardless
+        if self.is_failed() {
+            self.queued_moves.clear();
+            return;
+        }
+
+        self.time_until_next_queued_move = self.time_until_next_queued_move.saturating_sub(delta);
+        if !self.time_until_next_queued_move.is_zero() {
+            return;
+        }
+
+        if let Some(direction) = self.queued_moves.pop_front() {
+            let action_count = self.action_count();
+            self.move_player(direction, context);
+
+            // Stop walking if something got in the way
+            if self.action_count() == action_count {
+                self.queued_moves.clear();
+            }
+        }
+        self.time_until_next_queued_move = QUEUED_MOVE_INTERVAL;
+    }
+
+    /// Converts a point in level coordinates into the cell that contains it.
+    pub fn cell_at(&self, point: Vector2f) -> Vector2i {
+        let cell = point.cwise_div(self.grid_size);
+        Vector2i::new(cell.x.floor() as i32, cell.y.floor() as i32)
+    }
+
+    /// Makes the player walk to the given cell over the following frames, along the shortest path
+    /// that does not push any crates. Returns false if the cell is not reachable, or not without
+    /// running out of moves.
+    pub fn walk_to(&mut self, target: Vector2i) -> bool {
+        let remaining_moves = self.remaining_moves().unwrap_or(usize::MAX);
+        match self.find_path(self.player.position(), target) {
+            Some(path) if path.len() <= remaining_moves => {
+                self.queue_moves(path);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Replaces the queued moves with the ones given, starting to perform them right away.
+    fn queue_moves(&mut self, moves: Vec<Direction>) {
+        self.queued_moves = moves.into();
+        self.time_until_next_queued_move = Duration::ZERO;
+    }
+
+    /// Reacts to the player clicking on a cell. Clicking on a crate selects it, and clicking on
+    /// one of its reachable destinations afterwards pushes it there; clicking anywhere else walks
+    /// towards the cell clicked.
+    pub fn handle_click(&mut self, cell: Vector2i) {
+        self.queued_moves.clear();
+        self.buffered_move = None;
+
+        if let Some((plans, grab_offset)) = self.crate_selection.take() {
+            if let Some(plan) = plans.plan_to(cell - grab_offset) {
+                self.queue_moves(plan.to_vec());
+                return;
+            }
+
+            if self.crates[plans.crate_idx].occupies(cell) {
+                // Clicking the selected crate again deselects it
+                return;
+            }
+        }
+
+        let clicked_crate = self
+            .crates
+            .iter()
+            .position(|c| c.occupies(cell) && !c.in_hole());
+        if let Some(crate_idx) = clicked_crate {
+            let plans = self.plan_crate_pushes(crate_idx);
+            let grab_offset = cell - self.crates[crate_idx].position();
+            self.crate_selection = Some((plans, grab_offset));
+        } else {
+            self.walk_to(cell);
+        }
     }
 
     fn update_crate_opacity(&mut self) {
@@ -333,7 +620,12 @@ impl Level<'_> {
             for c in 0..crates.len() {
                 if crates[c].in_hole() {
                     for c_on_top in 0..crates.len() {
-                        if c != c_on_top && crates[c_on_top].position() == crates[c].position() {
+                        if c != c_on_top
+                            && !crates[c_on_top].in_hole()
+                            && crates[c]
+                                .cells()
+                                .any(|cell| crates[c_on_top].occupies(cell))
+                        {
                             crates_on_top.push(c_on_top);
                         }
                     }
@@ -350,6 +642,11 @@ impl Level<'_> {
             self.crates[c].set_opaque(false);
         });
 
+        for c in 0..self.crates.len() {
+            let depth = self.crate_depth_below_floor(c);
+            self.crates[c].set_depth_below_floor(depth);
+        }
+
         self.goals.iter_mut().for_each(|g| g.set_done(false));
         self.crates.iter_mut().for_each(|c| {
             if !c.in_hole() {
@@ -357,37 +654,127 @@ impl Level<'_> {
             }
         });
 
+        // Goals are satisfied per cell, so multi-cell crates may fill several of them at once
         self.goals.iter_mut().for_each(|g| {
-            if let Some(c) = self.crates.iter_mut().find(|c| {
-                c.position() == g.position()
-                    && !c.in_hole()
-                    && g.accepted_style().accepts(c.style())
+            if let Some((c, cell)) = self.crates.iter_mut().find_map(|c| {
+                if c.in_hole() || !g.accepted_style().accepts(c.style()) {
+                    return None;
+                }
+                let cell = c.cell_index(g.position())?;
+                Some((c, cell))
             }) {
                 g.set_done(true);
-                c.set_is_positioned(true);
+                c.set_cell_positioned(cell, true);
             };
         })
     }
 
     /// Moves the player one tile onto the given direction, if possible.
     pub fn move_player(&mut self, direction: Direction, context: &mut Context) {
-        let action = Action::Push {
-            direction,
-            look_direction: direction,
-        };
-        if let Ok(undo) = action.apply(self) {
-            self.undo_history.push(undo);
+        if self.step(direction, false) {
             play_move_sound(context);
         }
     }
 
+    /// Moves the player one tile onto the given direction, dragging along the crate right behind
+    /// it if there is any. Falls back to a regular move if there is no crate to pull.
+    pub fn pull_player(&mut self, direction: Direction, context: &mut Context) {
+        if self.step(direction, true) {
+            play_move_sound(context);
+        }
+    }
+
+    /// Moves the player one tile onto the given direction, pushing any crate in the way or, if
+    /// `pull` is set, dragging along the crate right behind it. Returns whether the player moved.
+    ///
+    /// Unlike [`Level::move_player`] and [`Level::pull_player`], this does not play any sounds nor
+    /// check whether pulling is enabled in the level.
+    pub fn step(&mut self, direction: Direction, pull: bool) -> bool {
+        let cell_to_pull_from = self.player.position() - Vector2i::from(direction);
+        let is_there_crate_to_pull = pull
+            && self
+                .crates
+                .iter()
+                .any(|c| c.occupies(cell_to_pull_from) && !c.in_hole());
+
+        let action = if is_there_crate_to_pull {
+            Action::Pull {
+                direction,
+                look_direction: direction.inverse(),
+                restyle: None,
+            }
+        } else {
+            Action::Push {
+                direction,
+                look_direction: direction,
+                restyle: None,
+            }
+        };
+
+        let Ok(undo) = action.apply(self) else {
+            return false;
+        };
+        self.undo_history.push(HistoryEntry::Action {
+            undo,
+            pulled: is_there_crate_to_pull,
+        });
+        self.update_crate_opacity();
+
+        true
+    }
+
+    /// Changes the building tile in the given cell to the one with the given id in the level's
+    /// tilesheet, or removes it if `None`, updating the cell's type and only the quads of the mesh
+    /// showing it.
+    ///
+    /// The change is recorded as its own entry of the undo history, which doesn't count as a move
+    /// and is reverted along with the action performed before it, if any.
+    ///
+    /// Fails if the player or a crate is on the cell, since they can't be moved out of the way of a
+    /// wall or be made to fall into a hole.
+    pub fn set_tile(&mut self, position: Vector2i, id: Option<u32>) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.player.position() != position && !self.crates.iter().any(|c| c.occupies(position)),
+            "cell {:?} is occupied",
+            position
+        );
+
+        let previous_id = self.replace_tile(position, id)?;
+        self.undo_history.push(HistoryEntry::TileChange {
+            position,
+            previous_id,
+        });
+
+        Ok(())
+    }
+
+    /// Changes a building tile like [`Level::set_tile`] does, without recording it in the undo
+    /// history. Returns the id of the tile that was replaced.
+    fn replace_tile(&mut self, position: Vector2i, id: Option<u32>) -> anyhow::Result<Option<u32>> {
+        let tileset = self.tilesheet.tileset();
+        if let Some(id) = id {
+            anyhow::ensure!(
+                id < tileset.tilecount,
+                "there is no tile with id {} in the tilesheet",
+                id
+            );
+        }
+
+        self.tilemap
+            .set_tile(position, LevelTile::from_tile_id(id, tileset))
+            .ok_or_else(|| anyhow::anyhow!("cell {:?} is outside of the level", position))?;
+
+        let cell = (position.x + position.y * self.tilemap.size().x as i32) as usize;
+        Ok(self.mesh.set_building_tile(cell, id, self.tilesheet))
+    }
+
     /// Returns true if there is a solid tile or crate in the given position.
     pub fn is_cell_obstructed(&self, position: Vector2i) -> bool {
         let cell_tile_is_solid = self.tilemap.get_tile(position) == Some(LevelTile::Solid);
         let cell_has_crate = self
             .crates
             .iter()
-            .any(|c| c.position() == position && !c.in_hole());
+            .any(|c| c.occupies(position) && !c.in_hole());
         cell_tile_is_solid || cell_has_crate
     }
 
@@ -396,17 +783,96 @@ impl Level<'_> {
     pub fn is_cell_walkable(&self, position: Vector2i) -> bool {
         let tile = self.tilemap.get_tile(position);
         match tile {
-            Some(LevelTile::Hole) => {
-                let is_there_walkable_crate = self
+            Some(LevelTile::Hole { depth }) => {
+                let crates_in_hole = self
                     .crates
                     .iter()
-                    .any(|c| c.position() == position && c.in_hole());
-                is_there_walkable_crate
+                    .filter(|c| c.occupies(position) && c.in_hole())
+                    .count();
+                crates_in_hole >= depth as usize
             }
-            Some(LevelTile::Floor) => true,
+            Some(LevelTile::Floor | LevelTile::Paint(_)) => true,
             Some(LevelTile::Solid) | None => false,
         }
     }
+
+    /// Returns whether the crate with the given index could be moved by the given offset without
+    /// overlapping any solid tile or other crate.
+    fn can_crate_move(&self, crate_idx: usize, movement: Vector2i) -> bool {
+        let moved_crate = &self.crates[crate_idx];
+        moved_crate
+            .cells()
+            .map(|cell| cell + movement)
+            .filter(|cell| !moved_crate.occupies(*cell))
+            .all(|cell| !self.is_cell_obstructed(cell))
+    }
+
+    /// Changes the style of the crate with the given index to the one of the first paint tile it
+    /// is over, or to the style given if there is any. Returns the crate's previous style.
+    fn paint_crate(&mut self, crate_idx: usize, style: Option<CrateStyle>) -> CrateStyle {
+        let painted_crate = &self.crates[crate_idx];
+        let previous_style = painted_crate.style();
+
+        let style = style.or_else(|| {
+            if painted_crate.in_hole() {
+                return None;
+            }
+            painted_crate
+                .cells()
+                .find_map(|cell| match self.tilemap.get_tile(cell) {
+                    Some(LevelTile::Paint(style)) => Some(style),
+                    _ => None,
+                })
+        });
+
+        if let Some(style) = style.filter(|s| *s != previous_style) {
+            if let Err(err) = self.crates[crate_idx].set_style(style) {
+                log::warn!("could not paint crate: {}", err);
+            }
+        }
+
+        previous_style
+    }
+
+    /// Returns how many crates would be below the crate with the given index if it fell into
+    /// the holes it is over, or `None` if any of its cells is not over a hole that still has room
+    /// for it.
+    fn hole_level_for_crate(&self, crate_idx: usize) -> Option<u32> {
+        self.crates[crate_idx]
+            .cells()
+            .map(|cell| {
+                let depth = match self.tilemap.get_tile(cell) {
+                    Some(LevelTile::Hole { depth }) => depth,
+                    _ => return None,
+                };
+                let crates_in_hole = self
+                    .crates
+                    .iter()
+                    .enumerate()
+                    .filter(|(idx, c)| *idx != crate_idx && c.in_hole() && c.occupies(cell))
+                    .count() as u32;
+
+                (crates_in_hole < depth).then_some(crates_in_hole)
+            })
+            .try_fold(0, |level, cell_level| Some(level.max(cell_level?)))
+    }
+
+    /// Returns how many crates should still be dropped above the given crate for it to be level
+    /// with the floor, if it is inside of a hole.
+    fn crate_depth_below_floor(&self, crate_idx: usize) -> u32 {
+        let c = &self.crates[crate_idx];
+        let Some(hole_level) = c.hole_level() else {
+            return 0;
+        };
+
+        c.cells()
+            .filter_map(|cell| match self.tilemap.get_tile(cell) {
+                Some(LevelTile::Hole { depth }) => Some(depth.saturating_sub(hole_level + 1)),
+                _ => None,
+            })
+            .min()
+            .unwrap_or(0)
+    }
 }
 
 impl<'s> Drawable for Level<'s> {
@@ -416,13 +882,18 @@ impl<'s> Drawable for Level<'s> {
         states: &sfml::graphics::RenderStates<'texture, 'shader, 'shader_texture>,
     ) {
         let mut level_rstate = *states;
-        level_rstate.set_texture(Some(self.tilesheet.texture()));
-        target.draw_primitives(&self.vertices, PrimitiveType::QUADS, &level_rstate);
+        level_rstate.set_texture(self.tilesheet.texture());
+        target.draw_primitives(&self.mesh.vertices, PrimitiveType::QUADS, &level_rstate);
 
-        // draw crates in holes (underground) first
-        self.crates
+        // draw crates in holes (underground) first, from the bottom of each stack upwards
+        let mut crates_in_holes = self
+            .crates
             .iter()
             .filter(|c| c.in_hole())
+            .collect::<Vec<_>>();
+        crates_in_holes.sort_by_key(|c| c.hole_level());
+        crates_in_holes
+            .into_iter()
             .for_each(|c| target.draw_with_renderstates(c, states));
 
         // then draw the ones on top of the ground
@@ -435,8 +906,31 @@ impl<'s> Drawable for Level<'s> {
             .iter()
             .for_each(|g| target.draw_with_renderstates(g, states));
 
+        // Highlight where the selected crate can be pushed to
+        if let Some((plans, grab_offset)) = &self.crate_selection {
+            let mut highlight = RectangleShape::with_size(self.grid_size);
+            highlight.set_fill_color(Color::rgba(0xff, 0xff, 0xff, 70));
+            for destination in plans.destinations() {
+                let cell = destination + *grab_offset;
+                highlight.set_position(
+                    Vector2f::new(cell.x as f32, cell.y as f32).cwise_mul(self.grid_size),
+                );
+                target.draw_with_renderstates(&highlight, states);
+            }
+
+            highlight.set_fill_color(Color::rgba(0xff, 0xff, 0x80, 110));
+            for cell in self.crates[plans.crate_idx].cells() {
+                highlight.set_position(
+                    Vector2f::new(cell.x as f32, cell.y as f32).cwise_mul(self.grid_size),
+                );
+                target.draw_with_renderstates(&highlight, states);
+            }
+        }
+
         target.draw_with_renderstates(&self.player, states);
 
+        target.draw_with_renderstates(&self.effects, states);
+
         for element in self.overlay.iter() {
             target.draw_with_renderstates(element.as_drawable(), states);
         }
@@ -470,3 +964,129 @@ pub fn camera_transform(
     x.translate(0., -tile * vertical_padding / 2.);
     x.inverse()
 }
+
+#[cfg(test)]
+mod tests {
+    use sfml::system::Vector2i;
+    use tiled::PropertyValue;
+
+    use super::{test_utils, tilemap::LevelTile, Direction, Level};
+
+    const CORRIDOR: &[&str] = &[
+        "##########", //
+        "#@     $.#", //
+        "##########", //
+    ];
+
+    #[test]
+    fn levels_fail_once_moves_exceed_the_limit() {
+        let tilesheet = test_utils::tilesheet();
+        let map = test_utils::map(CORRIDOR, &[("max_moves", PropertyValue::IntValue(3))]);
+        let mut level = Level::from_map_and_tilesheet(&map, &tilesheet).unwrap();
+
+        for _ in 0..3 {
+            assert!(!level.is_failed());
+            assert!(level.step(Direction::East, false));
+        }
+        // Using up every move is still fine
+        assert_eq!(level.action_count(), 3);
+        assert_eq!(level.remaining_moves(), Some(0));
+        assert!(!level.is_failed());
+
+        assert!(level.step(Direction::East, false));
+        assert!(level.is_failed());
+
+        // Undoing is still allowed after failing, and brings the level back within the limit
+        assert!(level.undo_step());
+        assert_eq!(level.action_count(), 3);
+        assert!(!level.is_failed());
+    }
+
+    #[test]
+    fn star_rating_depends_on_par() {
+        let tilesheet = test_utils::tilesheet();
+        let map = test_utils::map(CORRIDOR, &[]);
+        let level = Level::from_map_and_tilesheet(&map, &tilesheet).unwrap();
+        assert_eq!(level.star_rating(), None);
+
+        let map = test_utils::map(CORRIDOR, &[("par_moves", PropertyValue::IntValue(5))]);
+        let mut level = Level::from_map_and_tilesheet(&map, &tilesheet).unwrap();
+        assert_eq!(level.star_rating(), Some(3));
+
+        // Pace back and forth, checking the rating after each move
+        let expected_ratings = [3, 3, 3, 3, 3, 2, 2, 1, 1];
+        for (moves, expected_rating) in expected_ratings.into_iter().enumerate() {
+            let direction = if moves % 2 == 0 {
+                Direction::East
+            } else {
+                Direction::West
+            };
+            assert!(level.step(direction, false));
+            assert_eq!(level.star_rating(), Some(expected_rating));
+        }
+    }
+
+    #[test]
+    fn deep_holes_fill_up_and_empty_from_the_top() {
+        let tilesheet = test_utils::tilesheet();
+        let map = test_utils::map(
+            &[
+                "#######", //
+                "#@$_  #", //
+                "#     #", //
+                "#  $  #", //
+                "#     #", //
+                "#######", //
+            ],
+            &[],
+        );
+        let mut level = Level::from_map_and_tilesheet(&map, &tilesheet).unwrap();
+        let hole = Vector2i::new(3, 1);
+        level
+            .tilemap
+            .set_tile(hole, LevelTile::Hole { depth: 2 })
+            .unwrap();
+
+        // The first crate drops to the bottom of the hole, which can't be walked over yet
+        assert!(level.step(Direction::East, false));
+        assert_eq!(level.crates()[0].hole_level(), Some(0));
+        assert_eq!(level.crate_depth_below_floor(0), 1);
+        assert!(!level.is_cell_walkable(hole));
+
+        for direction in [
+            Direction::South,
+            Direction::South,
+            Direction::South,
+            Direction::East,
+            Direction::North,
+            Direction::North,
+        ] {
+            assert!(level.step(direction, false));
+        }
+
+        // The second one lands on top of it, level with the floor
+        assert_eq!(level.crates()[1].hole_level(), Some(1));
+        assert_eq!(level.crate_depth_below_floor(1), 0);
+        assert!(level.is_cell_walkable(hole));
+
+        // Pulling takes the topmost crate out of the hole
+        assert!(level.step(Direction::South, true));
+        assert_eq!(level.crates()[1].position(), Vector2i::new(3, 2));
+        assert!(!level.crates()[1].in_hole());
+        assert_eq!(level.crates()[0].hole_level(), Some(0));
+        assert!(!level.is_cell_walkable(hole));
+
+        // Undoing the pull drops it back on top
+        assert!(level.undo_step());
+        assert_eq!(level.crates()[1].hole_level(), Some(1));
+        assert_eq!(level.crates()[0].hole_level(), Some(0));
+        assert!(level.is_cell_walkable(hole));
+
+        // And undoing the push that dropped it takes it out again
+        assert!(level.undo_step());
+        assert_eq!(level.crates()[1].position(), Vector2i::new(3, 2));
+        assert!(!level.crates()[1].in_hole());
+        assert_eq!(level.crates()[0].hole_level(), Some(0));
+        assert!(!level.is_cell_walkable(hole));
+    }
+}
@@ -2,15 +2,15 @@
 
 #![allow(dead_code)]
 
-use std::{fmt::Display, num::NonZeroU32};
+use std::{fmt::Display, num::NonZeroU32, time::Duration};
 
 use sfml::{
-    graphics::{Drawable, Transformable},
+    graphics::{Color, Drawable, FloatRect, IntRect, PrimitiveType, Transformable, Vertex},
     system::{Vector2f, Vector2i},
 };
 use tiled::PropertyValue;
 
-use crate::{graphics::SpriteAtlas, graphics::Tilesheet};
+use crate::graphics::{PositionTween, QuadMeshable, SpriteAtlas, Tilesheet};
 
 pub(super) mod parsing;
 
@@ -33,6 +33,11 @@ impl Display for CrateStyleParseError {
 impl std::error::Error for CrateStyleParseError {}
 
 impl CrateStyle {
+    /// The number identifying this style in maps, which is never zero.
+    pub fn id(self) -> u32 {
+        self.0.get()
+    }
+
     pub(super) fn from_tiled_property(prop: &PropertyValue) -> Result<Self, CrateStyleParseError> {
         if let PropertyValue::IntValue(style) = prop {
             NonZeroU32::new(*style as u32)
@@ -66,14 +71,26 @@ impl Default for AcceptedCrateStyle {
     }
 }
 
-/// A crate the player can move around.
+/// A crate the player can move around. Crates may occupy more than a single cell, as described
+/// by their shape.
 #[derive(Clone)]
 pub struct Crate<'s> {
     position: Vector2i,
-    sprite_atlas: SpriteAtlas<'s>,
+    /// The offsets of the cells the crate occupies, relative to its position.
+    shape: Vec<Vector2i>,
+    /// One sprite atlas per cell in the crate's shape, in the same order.
+    sprite_atlases: Vec<SpriteAtlas<'s>>,
+    /// Quads tracing the outer edges of the crate's shape, relative to its position, so that
+    /// crates occupying several cells can be told apart from single crates next to each other.
+    /// Empty for single-cell crates.
+    outline: Vec<Vertex>,
+    /// Where the crate is drawn, which lags behind its position while it moves between cells.
+    tween: PositionTween,
     style: CrateStyle,
-    in_hole: bool,
+    /// How many crates were below this one when it fell into a hole, if it is inside of one.
+    hole_level: Option<u32>,
     grid_size: Vector2f,
+    tilesheet: &'s Tilesheet,
 }
 
 impl<'s> Crate<'s> {
@@ -81,13 +98,23 @@ impl<'s> Crate<'s> {
     const DROPPED_FRAME: usize = 1;
     const POSITIONED_FRAME: usize = 2;
     const TRANSLUCENT_ALPHA: u8 = 150;
+    const DEPTH_SHADE_STEP: u8 = 50;
+    const MAX_DEPTH_SHADE: u8 = 150;
+    const OUTLINE_COLOR: Color = Color::rgba(0x20, 0x18, 0x10, 200);
+    /// How thick the outline of crates occupying several cells is, relative to the grid size.
+    const OUTLINE_THICKNESS: f32 = 1. / 12.;
 
     pub fn new(
         position: Vector2i,
+        shape: &[Vector2i],
         tilesheet: &'s Tilesheet,
         id: u32,
         grid_size: Vector2f,
     ) -> Option<Self> {
+        if shape.is_empty() {
+            return None;
+        }
+
         let tile = tilesheet.tileset().get_tile(id)?;
 
         let crate_type = match tile.properties.get("style") {
@@ -95,6 +122,87 @@ impl<'s> Crate<'s> {
             None => None?,
         };
 
+        let frames = Self::frames_from_tile(tilesheet, id)?;
+
+        let sprite_atlases = shape
+            .iter()
+            .map(|&offset| {
+                let mut sprite_atlas =
+                    SpriteAtlas::with_texture_and_frames(tilesheet.texture(), &frames);
+                let cell = position + offset;
+                sprite_atlas
+                    .set_position(Vector2f::new(cell.x as f32, cell.y as f32).cwise_mul(grid_size));
+                sprite_atlas
+            })
+            .collect();
+
+        let tween = PositionTween::new(
+            Vector2f::new(position.x as f32, position.y as f32).cwise_mul(grid_size),
+            Duration::ZERO,
+        );
+
+        Some(Self {
+            position,
+            shape: shape.to_vec(),
+            style: crate_type,
+            sprite_atlases,
+            outline: Self::shape_outline(shape, grid_size),
+            tween,
+            hole_level: None,
+            grid_size,
+            tilesheet,
+        })
+    }
+
+    /// Builds the quads of the outline of a shape with more than one cell, along every edge of its
+    /// cells that doesn't border another one of them.
+    fn shape_outline(shape: &[Vector2i], grid_size: Vector2f) -> Vec<Vertex> {
+        let mut outline = Vec::new();
+        if shape.len() < 2 {
+            return outline;
+        }
+
+        let thickness = grid_size * Self::OUTLINE_THICKNESS;
+        for &offset in shape {
+            let cell_position =
+                Vector2f::new(offset.x as f32, offset.y as f32).cwise_mul(grid_size);
+            let edges = [
+                (
+                    Vector2i::new(0, -1),
+                    Vector2f::new(0., 0.),
+                    Vector2f::new(grid_size.x, thickness.y),
+                ),
+                (
+                    Vector2i::new(0, 1),
+                    Vector2f::new(0., grid_size.y - thickness.y),
+                    Vector2f::new(grid_size.x, thickness.y),
+                ),
+                (
+                    Vector2i::new(-1, 0),
+                    Vector2f::new(0., 0.),
+                    Vector2f::new(thickness.x, grid_size.y),
+                ),
+                (
+                    Vector2i::new(1, 0),
+                    Vector2f::new(grid_size.x - thickness.x, 0.),
+                    Vector2f::new(thickness.x, grid_size.y),
+                ),
+            ];
+            for (neighbour, edge_offset, size) in edges {
+                if !shape.contains(&(offset + neighbour)) {
+                    outline.add_quad(cell_position + edge_offset, size, FloatRect::default());
+                    outline.set_quad_color(outline.len() / 4 - 1, Self::OUTLINE_COLOR);
+                }
+            }
+        }
+
+        outline
+    }
+
+    /// Obtains the normal, dropped and positioned frames of the crate tile with the given id.
+    fn frames_from_tile(tilesheet: &Tilesheet, id: u32) -> Option<[IntRect; 3]> {
+        let tile = tilesheet.tileset().get_tile(id)?;
+
         let get_frame_id = |frame: usize| -> Option<u32> {
             let frames = &tile.animation.as_ref()?;
             Some(frames.get(frame)?.tile_id)
@@ -104,62 +212,120 @@ impl<'s> Crate<'s> {
         let dropped_tex_rect = tilesheet.tile_rect(get_frame_id(Self::DROPPED_FRAME)?)?;
         let positioned_tex_rect = tilesheet.tile_rect(get_frame_id(Self::POSITIONED_FRAME)?)?;
 
-        let sprite_atlas = {
-            let mut sprite_atlas = SpriteAtlas::with_texture_and_frames(
-                tilesheet.texture(),
-                &[normal_tex_rect, dropped_tex_rect, positioned_tex_rect],
-            );
-            sprite_atlas.set_position(
-                Vector2f::new(position.x as f32, position.y as f32).cwise_mul(grid_size),
-            );
-            sprite_atlas
-        };
-
-        Some(Self {
-            position,
-            style: crate_type,
-            sprite_atlas,
-            in_hole: false,
-            grid_size,
-        })
+        Some([normal_tex_rect, dropped_tex_rect, positioned_tex_rect])
     }
 
     pub fn position(&self) -> Vector2i {
         self.position
     }
 
+    /// Moves the crate to another position. The crate will be drawn moving towards it over time.
     pub fn set_position(&mut self, position: Vector2i) {
         self.position = position;
-        self.sprite_atlas.set_position(
+        self.tween.retarget(
             Vector2f::new(position.x as f32, position.y as f32).cwise_mul(self.grid_size),
         );
     }
 
+    /// Sets how long the crate takes to be drawn moving from a position to another.
+    pub fn set_move_duration(&mut self, duration: Duration) {
+        self.tween.set_duration(duration);
+    }
+
+    /// Sets whether the crate is drawn on whole world units while moving, so that pixel art
+    /// doesn't shimmer.
+    pub fn set_pixel_snapping(&mut self, snap: bool) {
+        self.tween.set_rounded(snap);
+    }
+
+    /// Advances the crate's movement animation.
+    pub fn update(&mut self, delta: Duration) {
+        self.tween.advance(delta);
+        let origin = self.tween.current();
+        for (offset, sprite_atlas) in self.shape.iter().zip(self.sprite_atlases.iter_mut()) {
+            sprite_atlas.set_position(
+                origin + Vector2f::new(offset.x as f32, offset.y as f32).cwise_mul(self.grid_size),
+            );
+        }
+    }
+
+    /// Iterates over the positions of the cells this crate occupies.
+    pub fn cells(&self) -> impl Iterator<Item = Vector2i> + '_ {
+        self.shape.iter().map(|offset| self.position + *offset)
+    }
+
+    /// Returns the index of the cell of this crate that is at the given position, if any.
+    pub fn cell_index(&self, position: Vector2i) -> Option<usize> {
+        self.cells().position(|cell| cell == position)
+    }
+
+    /// Returns whether this crate occupies the given position or not.
+    pub fn occupies(&self, position: Vector2i) -> bool {
+        self.cell_index(position).is_some()
+    }
+
     pub fn in_hole(&self) -> bool {
-        self.in_hole
+        self.hole_level.is_some()
     }
 
-    pub fn set_in_hole(&mut self, in_hole: bool) {
-        self.in_hole = in_hole;
-        self.sprite_atlas
-            .set_frame(if in_hole {
-                Self::DROPPED_FRAME
-            } else {
-                Self::NORMAL_FRAME
-            })
-            .unwrap();
+    /// How many crates were below this one when it fell into a hole, if it is inside of one.
+    pub fn hole_level(&self) -> Option<u32> {
+        self.hole_level
+    }
+
+    /// Sets whether the crate is inside of a hole, and how many crates are below it if so.
+    pub fn set_in_hole(&mut self, hole_level: Option<u32>) {
+        self.hole_level = hole_level;
+        for sprite_atlas in self.sprite_atlases.iter_mut() {
+            sprite_atlas
+                .set_frame(if hole_level.is_some() {
+                    Self::DROPPED_FRAME
+                } else {
+                    Self::NORMAL_FRAME
+                })
+                .unwrap();
+        }
     }
 
     pub fn set_opaque(&mut self, val: bool) {
-        self.sprite_atlas.set_alpha(if val {
-            u8::MAX
-        } else {
-            Self::TRANSLUCENT_ALPHA
-        });
+        for sprite_atlas in self.sprite_atlases.iter_mut() {
+            sprite_atlas.set_alpha(if val {
+                u8::MAX
+            } else {
+                Self::TRANSLUCENT_ALPHA
+            });
+        }
+
+        let mut outline_color = Self::OUTLINE_COLOR;
+        if !val {
+            outline_color.a = (outline_color.a as u16 * Self::TRANSLUCENT_ALPHA as u16 / 255) as u8;
+        }
+        for quad in 0..self.outline.len() / 4 {
+            self.outline.set_quad_color(quad, outline_color);
+        }
+    }
+
+    /// Darkens the crate according to how many levels below the floor it is.
+    pub fn set_depth_below_floor(&mut self, depth: u32) {
+        let shade = (depth.min(u8::MAX as u32) as u8)
+            .saturating_mul(Self::DEPTH_SHADE_STEP)
+            .min(Self::MAX_DEPTH_SHADE);
+        let brightness = u8::MAX - shade;
+        for sprite_atlas in self.sprite_atlases.iter_mut() {
+            sprite_atlas.set_tint(Color::rgb(brightness, brightness, brightness));
+        }
     }
 
+    /// Sets whether all of the crate's cells are on top of a goal that accepts them or not.
     pub fn set_is_positioned(&mut self, val: bool) {
-        self.sprite_atlas
+        for cell in 0..self.sprite_atlases.len() {
+            self.set_cell_positioned(cell, val);
+        }
+    }
+
+    /// Sets whether a single cell of the crate is on top of a goal that accepts it or not.
+    pub fn set_cell_positioned(&mut self, cell: usize, val: bool) {
+        self.sprite_atlases[cell]
             .set_frame(if val {
                 Self::POSITIONED_FRAME
             } else {
@@ -172,6 +338,35 @@ impl<'s> Crate<'s> {
     pub fn style(&self) -> CrateStyle {
         self.style
     }
+
+    /// Changes the crate's style, updating its sprites to the ones of the crate tile in the
+    /// tilesheet with that style.
+    pub fn set_style(&mut self, style: CrateStyle) -> anyhow::Result<()> {
+        let tile_id = self
+            .tilesheet
+            .tileset()
+            .tiles()
+            .find(|(_id, tile)| {
+                tile.user_type.as_deref() == Some("crate")
+                    && tile
+                        .properties
+                        .get("style")
+                        .and_then(|x| CrateStyle::from_tiled_property(x).ok())
+                        == Some(style)
+            })
+            .map(|(id, _tile)| id)
+            .ok_or_else(|| anyhow::anyhow!("there is no crate tile with the given style"))?;
+
+        let frames = Self::frames_from_tile(self.tilesheet, tile_id)
+            .ok_or_else(|| anyhow::anyhow!("could not obtain crate frames for the given style"))?;
+
+        for sprite_atlas in self.sprite_atlases.iter_mut() {
+            sprite_atlas.set_frames(&frames);
+        }
+        self.style = style;
+
+        Ok(())
+    }
 }
 
 impl<'s> Drawable for Crate<'s> {
@@ -180,7 +375,17 @@ impl<'s> Drawable for Crate<'s> {
         target: &mut dyn sfml::graphics::RenderTarget,
         states: &sfml::graphics::RenderStates<'texture, 'shader, 'shader_texture>,
     ) {
-        self.sprite_atlas.draw(target, states);
+        for sprite_atlas in self.sprite_atlases.iter() {
+            sprite_atlas.draw(target, states);
+        }
+
+        if !self.outline.is_empty() {
+            let origin = self.tween.current();
+            let mut outline_states = *states;
+            outline_states.set_texture(None);
+            outline_states.transform.translate(origin.x, origin.y);
+            target.draw_primitives(&self.outline, PrimitiveType::QUADS, &outline_states);
+        }
     }
 }
 
@@ -1,8 +1,14 @@
+use std::collections::HashSet;
+
 use sfml::system::{Vector2f, Vector2i};
+use tiled::PropertyValue;
 
 use crate::graphics::Tilesheet;
 
-use super::{Crate, Goal};
+use super::{
+    super::{CrateShapeError, Direction, LevelLoadError},
+    Crate, Goal,
+};
 
 pub enum MapObject<'s> {
     Spawn { position: Vector2i },
@@ -16,7 +22,9 @@ impl<'s> MapObject<'s> {
         object: &tiled::Object,
         map: &tiled::Map,
         tilesheet: &'s Tilesheet,
-    ) -> Option<Self> {
+    ) -> Result<Self, LevelLoadError> {
+        let invalid_object = || LevelLoadError::InvalidObject((**object).clone());
+
         let position = Vector2i::new(
             (object.x / map.tile_width as f32) as i32,
             (object.y / map.tile_height as f32) as i32,
@@ -28,14 +36,139 @@ impl<'s> MapObject<'s> {
         let grid_size = Vector2f::new(map.tile_width as f32, map.tile_height as f32);
 
         match object_type {
-            Some("spawn") => Some(MapObject::Spawn { position }),
-            Some("crate") => Some(MapObject::Crate(
-                Crate::new(position, tilesheet, tile_id, grid_size).expect("crate creation"),
-            )),
-            Some("goal") => Some(MapObject::Goal(
+            Some("spawn") => Ok(MapObject::Spawn { position }),
+            Some("crate") => {
+                let invalid_shape = |source| LevelLoadError::InvalidCrateShape {
+                    id: object.id(),
+                    name: object.name.clone(),
+                    source,
+                };
+                let shape = match object.properties.get("shape") {
+                    Some(PropertyValue::StringValue(shape)) => {
+                        parse_crate_shape(shape).map_err(invalid_shape)?
+                    }
+                    Some(_) => return Err(invalid_object()),
+                    None => vec![Vector2i::new(0, 0)],
+                };
+
+                Ok(MapObject::Crate(
+                    Crate::new(position, &shape, tilesheet, tile_id, grid_size)
+                        .expect("crate creation"),
+                ))
+            }
+            Some("goal") => Ok(MapObject::Goal(
                 Goal::new(position, tilesheet, tile_id, grid_size).expect("goal creation"),
             )),
-            _ => None,
+            _ => Err(invalid_object()),
+        }
+    }
+}
+
+/// Parses the `shape` property of a crate object into the offsets of the cells it occupies.
+///
+/// Shapes are written as rows separated by `/`, where `#` marks an occupied cell and `.` an empty
+/// one; e.g. `##` is a 2x1 crate and `#./##` is an L-shaped one. Every row must have at least one
+/// cell, and the occupied cells must all be connected to each other through their sides.
+fn parse_crate_shape(shape: &str) -> Result<Vec<Vector2i>, CrateShapeError> {
+    let mut cells = Vec::new();
+
+    for (y, row) in shape.split('/').enumerate() {
+        let row = row.trim();
+        if row.is_empty() {
+            return Err(CrateShapeError::EmptyRow(y));
+        }
+
+        for (x, cell) in row.chars().enumerate() {
+            match cell {
+                '#' => cells.push(Vector2i::new(x as i32, y as i32)),
+                '.' => (),
+                _ => return Err(CrateShapeError::InvalidCharacter(cell)),
+            }
         }
     }
+
+    let Some(first_cell) = cells.first() else {
+        return Err(CrateShapeError::NoCells);
+    };
+
+    // Flood fill from any cell, which should reach every other one
+    let mut reached = HashSet::from([(first_cell.x, first_cell.y)]);
+    let mut frontier = vec![*first_cell];
+    while let Some(cell) = frontier.pop() {
+        for neighbor in Direction::ALL.map(|direction| cell + Vector2i::from(direction)) {
+            if cells.contains(&neighbor) && reached.insert((neighbor.x, neighbor.y)) {
+                frontier.push(neighbor);
+            }
+        }
+    }
+    if reached.len() != cells.len() {
+        return Err(CrateShapeError::Disconnected);
+    }
+
+    Ok(cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use sfml::system::Vector2i;
+
+    use super::{parse_crate_shape, CrateShapeError};
+
+    #[test]
+    fn valid_shapes_are_parsed() {
+        assert_eq!(parse_crate_shape("#").unwrap(), [Vector2i::new(0, 0)]);
+        assert_eq!(
+            parse_crate_shape("##").unwrap(),
+            [Vector2i::new(0, 0), Vector2i::new(1, 0)]
+        );
+        assert_eq!(
+            parse_crate_shape("#. / ##").unwrap(),
+            [
+                Vector2i::new(0, 0),
+                Vector2i::new(0, 1),
+                Vector2i::new(1, 1)
+            ]
+        );
+        assert_eq!(
+            parse_crate_shape(".#/##/#.").unwrap(),
+            [
+                Vector2i::new(1, 0),
+                Vector2i::new(0, 1),
+                Vector2i::new(1, 1),
+                Vector2i::new(0, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_shapes_are_rejected() {
+        assert!(matches!(
+            parse_crate_shape("#./.#"),
+            Err(CrateShapeError::Disconnected)
+        ));
+        assert!(matches!(
+            parse_crate_shape("#.#"),
+            Err(CrateShapeError::Disconnected)
+        ));
+        assert!(matches!(
+            parse_crate_shape("#//#"),
+            Err(CrateShapeError::EmptyRow(1))
+        ));
+        assert!(matches!(
+            parse_crate_shape("##/"),
+            Err(CrateShapeError::EmptyRow(1))
+        ));
+        assert!(matches!(
+            parse_crate_shape(""),
+            Err(CrateShapeError::EmptyRow(0))
+        ));
+        assert!(matches!(
+            parse_crate_shape("../.."),
+            Err(CrateShapeError::NoCells)
+        ));
+        assert!(matches!(
+            parse_crate_shape("#x"),
+            Err(CrateShapeError::InvalidCharacter('x'))
+        ));
+    }
 }
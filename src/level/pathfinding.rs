@@ -0,0 +1,257 @@
+//! Pathfinding for moving the player and crates around a level automatically.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use sfml::system::Vector2i;
+
+use super::{tilemap::LevelTile, Direction, Level};
+
+/// A cell position usable as a hash map key.
+type CellKey = (i32, i32);
+
+fn key(cell: Vector2i) -> CellKey {
+    (cell.x, cell.y)
+}
+
+/// The plans to push a crate to every position it can be moved to.
+#[derive(Clone)]
+pub struct CratePushPlans {
+    /// The index of the crate to push.
+    pub crate_idx: usize,
+    /// The moves to perform to get the crate's position to each destination.
+    pub plans: HashMap<CellKey, Vec<Direction>>,
+}
+
+impl CratePushPlans {
+    /// Obtains the moves to perform to bring the crate to a given position, if possible.
+    pub fn plan_to(&self, position: Vector2i) -> Option<&[Direction]> {
+        self.plans.get(&key(position)).map(Vec::as_slice)
+    }
+
+    /// Iterates over the positions the crate can be brought to.
+    pub fn destinations(&self) -> impl Iterator<Item = Vector2i> + '_ {
+        self.plans.keys().map(|(x, y)| Vector2i::new(*x, *y))
+    }
+}
+
+impl Level<'_> {
+    /// Explores every cell reachable from a given one going only through the cells deemed free,
+    /// returning the direction each of them was reached from.
+    fn explore(
+        from: Vector2i,
+        is_cell_free: impl Fn(Vector2i) -> bool,
+    ) -> HashMap<CellKey, Direction> {
+        // Breadth-first search, keeping track of how we reached each cell
+        let mut came_from = HashMap::new();
+        let mut frontier = VecDeque::from([from]);
+        while let Some(cell) = frontier.pop_front() {
+            for direction in Direction::ALL {
+                let next = cell + Vector2i::from(direction);
+                if next != from && !came_from.contains_key(&key(next)) && is_cell_free(next) {
+                    came_from.insert(key(next), direction);
+                    frontier.push_back(next);
+                }
+            }
+        }
+
+        came_from
+    }
+
+    /// Reconstructs the path to a cell from the result of [`Level::explore`].
+    fn path_to(
+        came_from: &HashMap<CellKey, Direction>,
+        from: Vector2i,
+        to: Vector2i,
+    ) -> Option<Vec<Direction>> {
+        let mut path = Vec::new();
+        let mut cell = to;
+        while cell != from {
+            let direction = *came_from.get(&key(cell))?;
+            path.push(direction);
+            cell -= Vector2i::from(direction);
+        }
+        path.reverse();
+
+        Some(path)
+    }
+
+    /// Finds the shortest sequence of moves from a cell to another one that only goes through
+    /// walkable cells without crates or solid tiles.
+    pub fn find_path(&self, from: Vector2i, to: Vector2i) -> Option<Vec<Direction>> {
+        let is_cell_free =
+            |cell: Vector2i| self.is_cell_walkable(cell) && !self.is_cell_obstructed(cell);
+        if !is_cell_free(to) {
+            return None;
+        }
+
+        Self::path_to(&Self::explore(from, is_cell_free), from, to)
+    }
+
+    /// Finds every position the crate with the given index can be pushed to without moving any
+    /// other crate, along with the moves needed to do so.
+    ///
+    /// Crates that would fall into a hole or be painted another style are not pushed any further,
+    /// and plans needing more moves than the level has left are left out.
+    pub fn plan_crate_pushes(&self, crate_idx: usize) -> CratePushPlans {
+        let pushed_crate = &self.crates[crate_idx];
+        let remaining_moves = self.remaining_moves().unwrap_or(usize::MAX);
+        let start = (pushed_crate.position(), self.player.position());
+        let shape = pushed_crate
+            .cells()
+            .map(|cell| cell - pushed_crate.position())
+            .collect::<Vec<_>>();
+
+        let occupies_at =
+            |origin: Vector2i, cell: Vector2i| shape.iter().any(|offset| origin + *offset == cell);
+        let is_cell_obstructed_by_others = |cell: Vector2i| {
+            self.tilemap
+                .get_tile(cell)
+                .is_none_or(|t| t == LevelTile::Solid)
+                || self
+                    .crates
+                    .iter()
+                    .enumerate()
+                    .any(|(idx, c)| idx != crate_idx && !c.in_hole() && c.occupies(cell))
+        };
+        let would_fall_into_hole = |origin: Vector2i| {
+            shape.iter().all(|offset| {
+                let cell = origin + *offset;
+                let crates_in_hole = self
+                    .crates
+                    .iter()
+                    .filter(|c| c.in_hole() && c.occupies(cell))
+                    .count();
+                matches!(
+                    self.tilemap.get_tile(cell),
+                    Some(LevelTile::Hole { depth }) if crates_in_hole < depth as usize
+                )
+            })
+        };
+        let would_be_painted = |origin: Vector2i| {
+            shape.iter().any(|offset| {
+                matches!(
+                    self.tilemap.get_tile(origin + *offset),
+                    Some(LevelTile::Paint(style)) if style != pushed_crate.style()
+                )
+            })
+        };
+
+        let mut plans = HashMap::new();
+        let mut visited = HashSet::from([(key(start.0), key(start.1))]);
+        let mut frontier = VecDeque::from([(start.0, start.1, Vec::new())]);
+        while let Some((origin, player, moves)) = frontier.pop_front() {
+            let came_from = Self::explore(player, |cell| {
+                self.is_cell_walkable(cell)
+                    && !is_cell_obstructed_by_others(cell)
+                    && !occupies_at(origin, cell)
+            });
+
+            for direction in Direction::ALL {
+                let movement = Vector2i::from(direction);
+
+                for offset in shape.iter() {
+                    let pusher = origin + *offset - movement;
+                    let new_player = pusher + movement;
+                    let new_origin = origin + movement;
+
+                    let can_push = !occupies_at(origin, pusher)
+                        && self.is_cell_walkable(new_player)
+                        && shape
+                            .iter()
+                            .all(|offset| !is_cell_obstructed_by_others(new_origin + *offset));
+                    if !can_push || visited.contains(&(key(new_origin), key(new_player))) {
+                        continue;
+                    }
+
+                    let Some(walk) = Self::path_to(&came_from, player, pusher) else {
+                        continue;
+                    };
+                    visited.insert((key(new_origin), key(new_player)));
+
+                    let mut new_moves = moves.clone();
+                    new_moves.extend(walk);
+                    new_moves.push(direction);
+                    if new_moves.len() > remaining_moves {
+                        continue;
+                    }
+
+                    if new_origin != start.0 {
+                        plans
+                            .entry(key(new_origin))
+                            .or_insert_with(|| new_moves.clone());
+                    }
+                    if !would_fall_into_hole(new_origin) && !would_be_painted(new_origin) {
+                        frontier.push_back((new_origin, new_player, new_moves));
+                    }
+                }
+            }
+        }
+
+        CratePushPlans { crate_idx, plans }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sfml::system::Vector2i;
+    use tiled::PropertyValue;
+
+    use super::super::{objects::CrateStyle, test_utils, tilemap::LevelTile, Level};
+
+    const ROOM: &[&str] = &[
+        "#######", //
+        "#@ $  #", //
+        "#     #", //
+        "#######", //
+    ];
+
+    #[test]
+    fn planned_pushes_bring_the_crate_to_its_destination() {
+        let tilesheet = test_utils::tilesheet();
+        let map = test_utils::map(ROOM, &[]);
+        let mut level = Level::from_map_and_tilesheet(&map, &tilesheet).unwrap();
+
+        let destination = Vector2i::new(5, 1);
+        let plans = level.plan_crate_pushes(0);
+        assert!(plans.plan_to(Vector2i::new(3, 1)).is_none());
+        assert!(plans.plan_to(Vector2i::new(1, 2)).is_some());
+
+        let plan = plans.plan_to(destination).unwrap();
+        assert_eq!(plan.len(), 3);
+        for direction in plan {
+            assert!(level.step(*direction, false));
+        }
+        assert_eq!(level.crates()[0].position(), destination);
+    }
+
+    #[test]
+    fn plans_fit_within_the_remaining_moves() {
+        let tilesheet = test_utils::tilesheet();
+        let map = test_utils::map(ROOM, &[("max_moves", PropertyValue::IntValue(2))]);
+        let level = Level::from_map_and_tilesheet(&map, &tilesheet).unwrap();
+
+        let plans = level.plan_crate_pushes(0);
+        assert!(plans.plan_to(Vector2i::new(4, 1)).is_some());
+        assert!(plans.plan_to(Vector2i::new(5, 1)).is_none());
+        assert!(plans
+            .destinations()
+            .all(|d| plans.plan_to(d).unwrap().len() <= 2));
+    }
+
+    #[test]
+    fn crates_are_not_pushed_past_paint() {
+        let tilesheet = test_utils::tilesheet();
+        let map = test_utils::map(ROOM, &[]);
+        let mut level = Level::from_map_and_tilesheet(&map, &tilesheet).unwrap();
+
+        let style = CrateStyle::from_tiled_property(&PropertyValue::IntValue(2)).unwrap();
+        level
+            .tilemap
+            .set_tile(Vector2i::new(4, 1), LevelTile::Paint(style))
+            .unwrap();
+
+        let plans = level.plan_crate_pushes(0);
+        assert!(plans.plan_to(Vector2i::new(4, 1)).is_some());
+        assert!(plans.plan_to(Vector2i::new(5, 1)).is_none());
+    }
+}
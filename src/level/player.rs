@@ -1,10 +1,12 @@
+use std::time::Duration;
+
 use sfml::{
     graphics::{Drawable, Rect, Transformable},
     system::{Vector2f, Vector2i},
 };
 use tiled::PropertyValue;
 
-use crate::graphics::{SpriteAtlas, Tilesheet};
+use crate::graphics::{FrameAnimation, PositionTween, SpriteAtlas, Tilesheet};
 
 use super::Direction;
 
@@ -13,6 +15,15 @@ use super::Direction;
 pub struct Player<'s> {
     position: Vector2i,
     atlas: SpriteAtlas<'s>,
+    /// Where the player is drawn, which lags behind its position while it moves between cells.
+    tween: PositionTween,
+    /// The animations played while walking in each direction.
+    walk_animations: Vec<FrameAnimation>,
+    /// The animations played while pushing or pulling crates in each direction.
+    push_animations: Vec<FrameAnimation>,
+    is_pushing: bool,
+    /// How long the player has been animated for, so that walk cycles carry on between moves.
+    animation_time: Duration,
     direction: Direction,
     grid_size: Vector2f,
 }
@@ -21,13 +32,16 @@ impl Player<'_> {
     pub fn new(position: Vector2i, tilesheet: &Tilesheet, grid_size: Vector2f) -> Option<Player> {
         let texture = tilesheet.texture();
 
-        let get_rect = |property_name: &str| -> Option<Rect<i32>> {
+        let get_tile_id = |property_name: &str| -> Option<u32> {
             let prop = tilesheet.tileset().properties.get(property_name)?;
             match prop {
-                PropertyValue::IntValue(x) => tilesheet.tile_rect(*x as u32),
+                PropertyValue::IntValue(x) => Some(*x as u32),
                 _ => None,
             }
         };
+        let get_rect = |property_name: &str| -> Option<Rect<i32>> {
+            tilesheet.tile_rect(get_tile_id(property_name)?)
+        };
 
         let north_frame = get_rect("player_up")?;
         let south_frame = get_rect("player_down")?;
@@ -38,13 +52,40 @@ impl Player<'_> {
             &[north_frame, south_frame, east_frame, west_frame],
         );
 
-        atlas
-            .set_position(Vector2f::new(position.x as f32, position.y as f32).cwise_mul(grid_size));
+        // Walk cycles are the Tiled animations of the idle tiles, while pushing uses the
+        // `player_<direction>_push` tiles if present
+        let mut walk_animations = Vec::new();
+        let mut push_animations = Vec::new();
+        for direction in ["up", "down", "right", "left"] {
+            let idle_tile = get_tile_id(&format!("player_{}", direction))?;
+            let walk_animation =
+                FrameAnimation::load_tile_into_atlas(tilesheet, idle_tile, &mut atlas)?;
+            let push_animation = match get_tile_id(&format!("player_{}_push", direction)) {
+                Some(push_tile) => {
+                    FrameAnimation::load_tile_into_atlas(tilesheet, push_tile, &mut atlas)?
+                }
+                None => walk_animation.clone(),
+            };
+
+            walk_animations.push(walk_animation);
+            push_animations.push(push_animation);
+        }
+
+        let tween = PositionTween::new(
+            Vector2f::new(position.x as f32, position.y as f32).cwise_mul(grid_size),
+            Duration::ZERO,
+        );
+        atlas.set_position(tween.current());
         atlas.set_frame(Direction::South as usize).unwrap();
 
         Some(Player {
             position,
             atlas,
+            tween,
+            walk_animations,
+            push_animations,
+            is_pushing: false,
+            animation_time: Duration::ZERO,
             direction: Direction::South,
             grid_size,
         })
@@ -55,13 +96,67 @@ impl Player<'_> {
         self.set_direction(direction);
     }
 
+    /// Moves the player to another cell. The player will be drawn moving towards it over time.
     pub fn set_position(&mut self, position: Vector2i) {
         self.position = position;
-        self.atlas.set_position(
+        self.tween.retarget(
             Vector2f::new(position.x as f32, position.y as f32).cwise_mul(self.grid_size),
         );
     }
 
+    /// Sets how long the player takes to be drawn moving from a cell to another.
+    pub fn set_move_duration(&mut self, duration: Duration) {
+        self.tween.set_duration(duration);
+    }
+
+    /// Sets whether the player is drawn on whole world units while moving, so that pixel art
+    /// doesn't shimmer.
+    pub fn set_pixel_snapping(&mut self, snap: bool) {
+        self.tween.set_rounded(snap);
+    }
+
+    /// Where the player is being drawn, which may be between cells while it moves.
+    pub fn draw_position(&self) -> Vector2f {
+        self.tween.current()
+    }
+
+    /// Whether the player is still being drawn moving towards its position.
+    pub fn is_moving(&self) -> bool {
+        !self.tween.is_finished()
+    }
+
+    /// Sets whether the player is moving a crate along with itself, showing its pushing pose while
+    /// it moves if so.
+    pub fn set_pushing(&mut self, is_pushing: bool) {
+        self.is_pushing = is_pushing;
+    }
+
+    /// Advances the player's movement and walk cycle animations.
+    pub fn update(&mut self, delta: Duration) {
+        self.tween.advance(delta);
+        self.atlas.set_position(self.tween.current());
+
+        let frame = if self.is_moving() {
+            let animations = if self.is_pushing {
+                &self.push_animations
+            } else {
+                &self.walk_animations
+            };
+            let animation = &animations[self.direction as usize];
+
+            // Walk cycles are made of two steps, and one step is taken per cell moved regardless
+            // of how long the cycle takes in Tiled
+            let step_length = animation.length().as_secs_f32() / 2.;
+            let rate = step_length / self.tween.duration().as_secs_f32();
+            self.animation_time += delta.mul_f32(rate);
+            animation.frame_at(self.animation_time)
+        } else {
+            // Idle frame
+            self.direction as usize
+        };
+        self.atlas.set_frame(frame).unwrap();
+    }
+
     pub fn position(&self) -> Vector2i {
         self.position
     }
@@ -0,0 +1,123 @@
+//! Helpers to build small levels out of text for unit tests.
+
+use std::{
+    fs::File,
+    io::{BufReader, Cursor, Read},
+    path::Path,
+};
+
+use tiled::{Loader, Map, PropertyValue};
+
+use crate::{assets::AssetManager, graphics::Tilesheet};
+
+/// Where test maps pretend to be, so the level tileset is found relative to them.
+const TEST_MAP_PATH: &str = "assets/levels/test_layout.tmx";
+
+/// Loads the tilesheet levels are made out of, without its texture.
+pub fn tilesheet() -> Tilesheet {
+    let tileset = AssetManager::load_level_tileset().expect("loading level tileset");
+    Tilesheet::headless_from_tileset(tileset).expect("loading level tilesheet")
+}
+
+/// Builds a map out of rows of characters, each being one of the following cells:
+///
+/// - `#`: A wall.
+/// - `_`: A hole.
+/// - `@`: The player's spawn.
+/// - `$`: A crate.
+/// - `.`: A goal.
+/// - `*`: A crate on a goal.
+/// - `1` to `5`: A paint tile painting crates of that style.
+///
+/// Any other character is a floor cell.
+pub fn map(rows: &[&str], properties: &[(&str, PropertyValue)]) -> Map {
+    let width = rows
+        .iter()
+        .map(|row| row.chars().count())
+        .max()
+        .unwrap_or(0);
+    let height = rows.len();
+
+    let mut floor = Vec::new();
+    let mut building = Vec::new();
+    let mut objects = Vec::new();
+    for (y, row) in rows.iter().enumerate() {
+        let mut cells = row.chars().chain(std::iter::repeat(' '));
+        for x in 0..width {
+            let cell = cells.next().unwrap();
+            floor.push(90);
+            building.push(match cell {
+                '#' => 85,
+                '_' => 12,
+                // Paint tiles are the last tile of each row of the tilesheet from the second one
+                style @ '1'..='5' => style.to_digit(10).unwrap() * 13 + 13,
+                _ => 0,
+            });
+            let object_gids: &[u32] = match cell {
+                '@' => &[73],
+                '$' => &[7],
+                '.' => &[40],
+                '*' => &[40, 7],
+                _ => &[],
+            };
+            for gid in object_gids {
+                objects.push(format!(
+                    r#"<object id="{}" gid="{}" x="{}" y="{}" width="128" height="128"/>"#,
+                    objects.len() + 1,
+                    gid,
+                    x * 128,
+                    y * 128
+                ));
+            }
+        }
+    }
+
+    let layer_data = |tiles: &[u32]| {
+        tiles
+            .chunks(width)
+            .map(|row| row.iter().map(u32::to_string).collect::<Vec<_>>().join(","))
+            .collect::<Vec<_>>()
+            .join(",\n")
+    };
+    let properties = properties
+        .iter()
+        .map(|(name, value)| {
+            let (kind, value) = match value {
+                PropertyValue::BoolValue(value) => ("bool", value.to_string()),
+                PropertyValue::IntValue(value) => ("int", value.to_string()),
+                _ => unimplemented!("unsupported test map property type"),
+            };
+            format!(r#"<property name="{name}" type="{kind}" value="{value}"/>"#)
+        })
+        .collect::<String>();
+
+    let tmx = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.5" orientation="orthogonal" renderorder="right-down" width="{width}" height="{height}" tilewidth="128" tileheight="128" infinite="0">
+ <properties>{properties}</properties>
+ <tileset firstgid="1" source="../tilesheets/sokoban_tilesheet.tsx"/>
+ <layer id="1" name="floor" width="{width}" height="{height}">
+  <data encoding="csv">{floor}</data>
+ </layer>
+ <layer id="2" name="building" width="{width}" height="{height}">
+  <data encoding="csv">{building}</data>
+ </layer>
+ <objectgroup id="3" name="objects">{objects}</objectgroup>
+</map>"#,
+        floor = layer_data(&floor),
+        building = layer_data(&building),
+        objects = objects.concat(),
+    );
+
+    // Serve the map from memory and everything else, i.e. the tileset, from the filesystem
+    let reader = move |path: &Path| -> std::io::Result<Box<dyn Read>> {
+        if path == Path::new(TEST_MAP_PATH) {
+            Ok(Box::new(Cursor::new(tmx.clone().into_bytes())))
+        } else {
+            Ok(Box::new(BufReader::new(File::open(path)?)))
+        }
+    };
+    Loader::with_reader(reader)
+        .load_tmx_map(TEST_MAP_PATH)
+        .expect("loading test map")
+}
@@ -0,0 +1,49 @@
+use sfml::{
+    graphics::{
+        BlendMode, Image, PrimitiveType, RenderStates, RenderTarget, RenderTexture, Transform,
+    },
+    system::Vector2f,
+};
+
+use super::Level;
+
+impl Level<'_> {
+    /// Renders a miniature of the level's floor, walls, crates and goals, scaled so that its
+    /// longest side is `max_side` pixels long. Returns `None` if the image could not be rendered.
+    pub fn render_thumbnail(&self, max_side: u32) -> Option<Image> {
+        let map_size = self.tilemap.size();
+        let map_px_size = Vector2f::new(
+            map_size.x as f32 * self.grid_size.x,
+            map_size.y as f32 * self.grid_size.y,
+        );
+        let scale = max_side as f32 / map_px_size.x.max(map_px_size.y);
+
+        let mut texture = RenderTexture::new(
+            ((map_px_size.x * scale).round() as u32).max(1),
+            ((map_px_size.y * scale).round() as u32).max(1),
+        )?;
+        texture.set_smooth(true);
+        texture.clear(self.background_color);
+
+        let mut transform = Transform::IDENTITY;
+        transform.scale(scale, scale);
+        let states = RenderStates::new(BlendMode::ALPHA, transform, None, None);
+
+        let mut level_states = states;
+        level_states.set_texture(self.tilesheet.texture());
+        texture.draw_primitives(&self.mesh.vertices, PrimitiveType::QUADS, &level_states);
+
+        let mut crates = self.crates.iter().collect::<Vec<_>>();
+        // Crates in holes go below the rest, from the bottom of each stack upwards
+        crates.sort_by_key(|c| (!c.in_hole(), c.hole_level()));
+        for c in crates {
+            texture.draw_with_renderstates(c, &states);
+        }
+        for g in self.goals.iter() {
+            texture.draw_with_renderstates(g, &states);
+        }
+
+        texture.display();
+        texture.texture().copy_to_image()
+    }
+}
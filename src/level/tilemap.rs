@@ -1,12 +1,59 @@
 use sfml::system::{Vector2i, Vector2u};
-use tiled::{LayerTileData, Tileset};
+use tiled::{LayerTileData, PropertyValue, Tileset};
 
-/// One of a level's tiles. Level tiles are inmutable because they are part of the mesh of it.
+use super::objects::CrateStyle;
+
+/// One of a level's tiles. Level tiles are part of the level's mesh, so they must be changed
+/// through [`Level::set_tile`](super::Level::set_tile) to keep it up to date.
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum LevelTile {
     Solid,
-    Hole,
+    /// A hole that needs `depth` crates to be dropped into it before it can be walked over.
+    Hole {
+        depth: u32,
+    },
     Floor,
+    /// A floor tile that changes the style of any crate pushed onto it.
+    Paint(CrateStyle),
+}
+
+impl LevelTile {
+    /// The type of the tile with the given id in a tileset, or of an empty cell if `None`.
+    pub fn from_tile_id(id: Option<u32>, tileset: &Tileset) -> Self {
+        let Some(id) = id else {
+            return LevelTile::Floor;
+        };
+        let tile_data = tileset.get_tile(id);
+
+        match tile_data.as_ref().and_then(|t| t.user_type.as_deref()) {
+            Some("solid") => LevelTile::Solid,
+            Some("hole") => {
+                let depth = match tile_data.as_ref().and_then(|t| t.properties.get("depth")) {
+                    Some(PropertyValue::IntValue(depth)) if *depth >= 1 => *depth as u32,
+                    Some(_) => {
+                        log::warn!("hole tile has an invalid `depth` property");
+                        1
+                    }
+                    None => 1,
+                };
+                LevelTile::Hole { depth }
+            }
+            Some("paint") => {
+                match tile_data
+                    .as_ref()
+                    .and_then(|t| t.properties.get("paints"))
+                    .map(CrateStyle::from_tiled_property)
+                {
+                    Some(Ok(style)) => LevelTile::Paint(style),
+                    _ => {
+                        log::warn!("paint tile has no valid `paints` property");
+                        LevelTile::Floor
+                    }
+                }
+            }
+            _ => LevelTile::Floor,
+        }
+    }
 }
 
 /// A bidimensional array of level tiles.
@@ -25,18 +72,7 @@ impl Tilemap {
     ) -> Self {
         let tiles = building_layer
             .iter()
-            .map(|tile| match tile {
-                Some(tile) => {
-                    let tile_data = tileset.get_tile(tile.id());
-
-                    match tile_data.as_ref().and_then(|t| t.user_type.as_deref()) {
-                        Some("solid") => LevelTile::Solid,
-                        Some("hole") => LevelTile::Hole,
-                        _ => LevelTile::Floor,
-                    }
-                }
-                None => LevelTile::Floor,
-            })
+            .map(|tile| LevelTile::from_tile_id(tile.as_ref().map(|t| t.id()), tileset))
             .collect::<Vec<_>>();
 
         Self { size, tiles }
@@ -49,8 +85,21 @@ impl Tilemap {
 
     /// Obtains a tile from the tilemap in a given position, if it exists.
     pub fn get_tile(&self, pos: Vector2i) -> Option<LevelTile> {
+        if pos.x < 0 || pos.y < 0 || pos.x >= self.size.x as i32 || pos.y >= self.size.y as i32 {
+            return None;
+        }
+
         self.tiles
             .get((pos.x + pos.y * self.size.x as i32) as usize)
             .copied()
     }
+
+    /// Replaces the tile in a given position, returning the previous one if the position is
+    /// within the tilemap.
+    pub(super) fn set_tile(&mut self, pos: Vector2i, tile: LevelTile) -> Option<LevelTile> {
+        self.get_tile(pos)?;
+
+        let idx = (pos.x + pos.y * self.size.x as i32) as usize;
+        Some(std::mem::replace(&mut self.tiles[idx], tile))
+    }
 }
@@ -1,15 +1,16 @@
-use std::{ops::ControlFlow, time::Duration};
+use std::{ops::ControlFlow, path::PathBuf};
 
 use anyhow::Context as AnyCtx;
 use assets::AssetManager;
-use context::{Context, SaveData};
+use context::{Config, Context, SaveData, Thumbnails};
 
-use input_system::InputSystem;
+use input_system::{InputSystem, Keymap};
+use recording::{Playback, RecordedEvent, RecordedFrame, Recorder, RecordingHeader};
 use sfml::{
-    graphics::RenderWindow,
-    window::{ContextSettings, Event, Style},
+    graphics::{FloatRect, RenderTarget, RenderWindow, View},
+    system::Vector2u,
+    window::{ContextSettings, Event, Style, VideoMode},
 };
-use sound_manager::SoundManager;
 use state::{LevelSelect, State};
 
 pub mod assets;
@@ -17,17 +18,64 @@ pub mod context;
 pub mod graphics;
 pub mod input_system;
 pub mod level;
+pub mod recording;
 pub mod sound_manager;
 pub mod state;
+pub mod tty;
 pub mod ui;
 
+/// Options given to the game through the command line.
+#[derive(Default)]
+struct Options {
+    /// Where to record the session's input to, given by `--record <path>`.
+    record: Option<PathBuf>,
+    /// The recording to play back instead of reading input from the window, given by
+    /// `--playback <path>`.
+    playback: Option<PathBuf>,
+    /// The directory to export level thumbnails to instead of starting the game, given by
+    /// `--export-thumbnails <path>`.
+    export_thumbnails: Option<PathBuf>,
+}
+
+impl Options {
+    fn from_args() -> anyhow::Result<Self> {
+        let mut options = Self::default();
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let target = match arg.as_str() {
+                "--record" => &mut options.record,
+                "--playback" => &mut options.playback,
+                "--export-thumbnails" => &mut options.export_thumbnails,
+                _ => anyhow::bail!("unknown argument: {}", arg),
+            };
+            let path = args
+                .next()
+                .with_context(|| format!("expected a path after {}", arg))?;
+            *target = Some(path.into());
+        }
+
+        Ok(options)
+    }
+}
+
 /// Run the game, returning on failure.
 pub fn run() -> anyhow::Result<()> {
     env_logger::init();
 
+    let options = Options::from_args()?;
+
     let assets = AssetManager::load().context("failed to load assets")?;
-    let mut window = create_window();
-    let sound = SoundManager::new();
+
+    if let Some(dir) = options.export_thumbnails {
+        let mut save_data = SaveData::default();
+        save_data.keep_in_memory();
+        let mut config = Config::from_config_file().unwrap_or_default();
+        config.keep_in_memory();
+        let context = Context::new(&assets, config, save_data, InputSystem::new());
+        return Thumbnails::export(&context, &dir).context("failed to export thumbnails");
+    }
+
     let completed_levels = match SaveData::from_savefile() {
         Ok(x) => x,
         Err(err) => {
@@ -35,34 +83,124 @@ pub fn run() -> anyhow::Result<()> {
             Default::default()
         }
     };
-    let input = InputSystem::new();
-
-    let mut context = Context {
-        assets: &assets,
-        completed_levels,
-        delta_time: Duration::default(),
-        sound,
-        input,
+    let config = match Config::from_config_file() {
+        Ok(x) => x,
+        Err(err) => {
+            log::warn!("could not load config: {}", err);
+            Default::default()
+        }
     };
+    let keymap = match Keymap::from_config_file() {
+        Ok(x) => x,
+        Err(err) => {
+            log::warn!("could not load keymap: {}", err);
+            Default::default()
+        }
+    };
+
+    let mut playback = options
+        .playback
+        .map(|path| Playback::open(&path).context("failed to open recording"))
+        .transpose()?;
+    let (completed_levels, keymap, config) = match &playback {
+        Some(playback) => {
+            // Start off exactly like the recorded session did, without touching the save file
+            // nor the config files
+            let header = playback.header();
+            let mut save_data = header.save_data.clone();
+            save_data.keep_in_memory();
+            let mut keymap = header.keymap.clone();
+            keymap.keep_in_memory();
+            let mut config = header.config.clone();
+            config.keep_in_memory();
+            config.fullscreen = false;
+            config.window_size = header.window_size;
+            (save_data, keymap, config)
+        }
+        None => (completed_levels, keymap, config),
+    };
+
+    let mut window = create_window(&config);
+
+    let mut recorder = options
+        .record
+        .map(|path| {
+            let header = RecordingHeader {
+                window_size: window.size(),
+                save_data: completed_levels.clone(),
+                keymap: keymap.clone(),
+                config: config.clone(),
+            };
+            Recorder::create(&path, &header).context("failed to create recording")
+        })
+        .transpose()?;
+
+    let input = InputSystem::with_keymap(keymap);
+
+    let mut context = Context::new(&assets, config, completed_levels, input);
+    context.thumbnails = Thumbnails::load(&context);
     let mut state: Box<dyn State> = Box::new(LevelSelect::new(&context)?);
 
     let mut last_frame_time = std::time::Instant::now();
     'outer: loop {
         let this_frame_time = std::time::Instant::now();
-        context.delta_time = this_frame_time - last_frame_time;
+
+        let frame = match playback.as_mut().map(Playback::next_frame).transpose()? {
+            Some(Some(frame)) => {
+                // Only closing the window is listened to while playing back
+                while let Some(event) = window.poll_event() {
+                    if event == Event::Closed {
+                        break 'outer;
+                    }
+                }
+
+                frame
+            }
+            recording_state => {
+                if recording_state.is_some() {
+                    log::info!("playback finished, handing control back to the player");
+                    playback = None;
+                }
+
+                let mut events = Vec::new();
+                while let Some(event) = window.poll_event() {
+                    if event == Event::Closed {
+                        break 'outer;
+                    }
+                    events.extend(RecordedEvent::from_event(&event));
+                }
+
+                RecordedFrame {
+                    delta_time: this_frame_time - last_frame_time,
+                    events,
+                }
+            }
+        };
+        context.delta_time = frame.delta_time;
 
         context.sound.update();
-        context.input.update(&window);
+        context.input.update(context.delta_time);
+        context
+            .input
+            .mouse_mut()
+            .set_ui_transform(ui::ui_transform(context.assets, window.size()));
 
         if let ControlFlow::Break(new_state) = state.tick(&mut context, &mut window) {
             state = new_state;
         }
 
-        while let Some(event) = window.poll_event() {
-            if event == Event::Closed {
-                break 'outer;
+        for event in frame.events.iter().map(RecordedEvent::to_event) {
+            if let Event::Resized { width, height } = event {
+                if playback.is_some() {
+                    window.set_size((width, height));
+                }
+                if !context.config.fullscreen {
+                    context.config.window_size = Vector2u::new(width, height);
+                }
             }
 
+            context.input.handle_event(&event);
+
             if let ControlFlow::Break(new_state) =
                 state.process_event(&mut context, &mut window, event)
             {
@@ -70,6 +208,12 @@ pub fn run() -> anyhow::Result<()> {
             }
         }
 
+        let recording_result = recorder.as_mut().map(|r| r.record_frame(&frame));
+        if let Some(Err(err)) = recording_result {
+            log::error!("could not record frame, stopping recording: {}", err);
+            recorder = None;
+        }
+
         state.draw(&mut context, &mut window);
 
         window.display();
@@ -77,19 +221,52 @@ pub fn run() -> anyhow::Result<()> {
         last_frame_time = this_frame_time;
     }
 
+    // Remember the window size for the next session
+    context.config.save();
+
     Ok(())
 }
 
-fn create_window() -> RenderWindow {
+/// The video mode and style of the window of the application for the given config.
+fn window_mode(config: &Config) -> (VideoMode, Style) {
+    if config.fullscreen {
+        (VideoMode::desktop_mode(), Style::FULLSCREEN)
+    } else {
+        (
+            VideoMode::from((config.window_size.x, config.window_size.y)),
+            Style::CLOSE | Style::RESIZE,
+        )
+    }
+}
+
+fn create_window(config: &Config) -> RenderWindow {
     // Create the window of the application
+    let (mode, style) = window_mode(config);
     let context_settings = ContextSettings::default();
-    let mut window = RenderWindow::new(
-        (1080, 720),
-        "Sokoban!",
-        Style::CLOSE | Style::RESIZE,
-        &context_settings,
-    );
-    window.set_vertical_sync_enabled(true);
+    let mut window = RenderWindow::new(mode, "Sokoban!", style, &context_settings);
+    apply_window_config(&mut window, config);
 
     window
 }
+
+/// Recreates the window of the application, which is needed to switch between fullscreen and
+/// windowed mode.
+pub(crate) fn recreate_window(window: &mut RenderWindow, config: &Config) {
+    let (mode, style) = window_mode(config);
+    window.recreate(mode, "Sokoban!", style, &ContextSettings::default());
+    window.set_view(&View::from_rect(FloatRect::new(
+        0.,
+        0.,
+        window.size().x as f32,
+        window.size().y as f32,
+    )));
+    apply_window_config(window, config);
+}
+
+/// Applies the settings of the config that can be changed without recreating the window.
+pub(crate) fn apply_window_config(window: &mut RenderWindow, config: &Config) {
+    window.set_vertical_sync_enabled(config.vsync);
+    window.set_framerate_limit(config.frame_cap);
+    // Held movement keys are repeated by the input system instead
+    window.set_key_repeat_enabled(false);
+}
@@ -0,0 +1,327 @@
+//! Recording of the events delivered to the game, so that sessions can be played back exactly,
+//! e.g. to reproduce bugs.
+//!
+//! Recordings are stored as RON, with a header on the first line followed by one line per frame.
+//! Every frame is flushed as soon as it is recorded, so recordings survive the game crashing.
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Lines, Write},
+    path::Path,
+    time::Duration,
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sfml::{
+    system::Vector2u,
+    window::{joystick, mouse, Event, Key},
+};
+
+use crate::{
+    context::{Config, SaveData},
+    input_system::Keymap,
+};
+
+/// Mirror of [`joystick::Axis`] used to (de)serialize it.
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "joystick::Axis")]
+enum AxisDef {
+    X,
+    Y,
+    Z,
+    R,
+    U,
+    V,
+    PovX,
+    PovY,
+}
+
+/// SFML doesn't export its scancode type, so scancodes are recorded by their RON representation
+/// and parsed back into whatever type [`Event`] expects.
+fn parse_scancode<T: DeserializeOwned>(scan: &str) -> T {
+    ron::from_str(scan).unwrap_or_else(|_| ron::from_str("Unknown").expect("unknown scancode"))
+}
+
+/// A serializable copy of the window events the game reacts to. See [`Event`] for what each of
+/// them means.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum RecordedEvent {
+    Resized {
+        width: u32,
+        height: u32,
+    },
+    LostFocus,
+    GainedFocus,
+    TextEntered {
+        unicode: char,
+    },
+    KeyPressed {
+        code: Key,
+        scan: String,
+        alt: bool,
+        ctrl: bool,
+        shift: bool,
+        system: bool,
+    },
+    KeyReleased {
+        code: Key,
+        alt: bool,
+        ctrl: bool,
+        shift: bool,
+        system: bool,
+    },
+    MouseWheelScrolled {
+        wheel: mouse::Wheel,
+        delta: f32,
+        x: i32,
+        y: i32,
+    },
+    MouseButtonPressed {
+        button: mouse::Button,
+        x: i32,
+        y: i32,
+    },
+    MouseButtonReleased {
+        button: mouse::Button,
+        x: i32,
+        y: i32,
+    },
+    MouseMoved {
+        x: i32,
+        y: i32,
+    },
+    MouseEntered,
+    MouseLeft,
+    JoystickButtonPressed {
+        joystickid: u32,
+        button: u32,
+    },
+    JoystickButtonReleased {
+        joystickid: u32,
+        button: u32,
+    },
+    JoystickMoved {
+        joystickid: u32,
+        #[serde(with = "AxisDef")]
+        axis: joystick::Axis,
+        position: f32,
+    },
+    JoystickConnected {
+        joystickid: u32,
+    },
+    JoystickDisconnected {
+        joystickid: u32,
+    },
+}
+
+impl RecordedEvent {
+    /// Creates a recorded copy of an event, if it is one the game can react to.
+    pub fn from_event(event: &Event) -> Option<Self> {
+        Some(match *event {
+            Event::Resized { width, height } => Self::Resized { width, height },
+            Event::LostFocus => Self::LostFocus,
+            Event::GainedFocus => Self::GainedFocus,
+            Event::TextEntered { unicode } => Self::TextEntered { unicode },
+            Event::KeyPressed {
+                code,
+                scan,
+                alt,
+                ctrl,
+                shift,
+                system,
+            } => Self::KeyPressed {
+                code,
+                scan: ron::to_string(&scan).ok()?,
+                alt,
+                ctrl,
+                shift,
+                system,
+            },
+            Event::KeyReleased {
+                code,
+                alt,
+                ctrl,
+                shift,
+                system,
+            } => Self::KeyReleased {
+                code,
+                alt,
+                ctrl,
+                shift,
+                system,
+            },
+            Event::MouseWheelScrolled { wheel, delta, x, y } => {
+                Self::MouseWheelScrolled { wheel, delta, x, y }
+            }
+            Event::MouseButtonPressed { button, x, y } => Self::MouseButtonPressed { button, x, y },
+            Event::MouseButtonReleased { button, x, y } => {
+                Self::MouseButtonReleased { button, x, y }
+            }
+            Event::MouseMoved { x, y } => Self::MouseMoved { x, y },
+            Event::MouseEntered => Self::MouseEntered,
+            Event::MouseLeft => Self::MouseLeft,
+            Event::JoystickButtonPressed { joystickid, button } => {
+                Self::JoystickButtonPressed { joystickid, button }
+            }
+            Event::JoystickButtonReleased { joystickid, button } => {
+                Self::JoystickButtonReleased { joystickid, button }
+            }
+            Event::JoystickMoved {
+                joystickid,
+                axis,
+                position,
+            } => Self::JoystickMoved {
+                joystickid,
+                axis,
+                position,
+            },
+            Event::JoystickConnected { joystickid } => Self::JoystickConnected { joystickid },
+            Event::JoystickDisconnected { joystickid } => Self::JoystickDisconnected { joystickid },
+            _ => return None,
+        })
+    }
+
+    /// Converts this back into the event it was recorded from.
+    pub fn to_event(&self) -> Event {
+        match *self {
+            Self::Resized { width, height } => Event::Resized { width, height },
+            Self::LostFocus => Event::LostFocus,
+            Self::GainedFocus => Event::GainedFocus,
+            Self::TextEntered { unicode } => Event::TextEntered { unicode },
+            Self::KeyPressed {
+                code,
+                ref scan,
+                alt,
+                ctrl,
+                shift,
+                system,
+            } => Event::KeyPressed {
+                code,
+                scan: parse_scancode(scan),
+                alt,
+                ctrl,
+                shift,
+                system,
+            },
+            Self::KeyReleased {
+                code,
+                alt,
+                ctrl,
+                shift,
+                system,
+            } => Event::KeyReleased {
+                code,
+                alt,
+                ctrl,
+                shift,
+                system,
+            },
+            Self::MouseWheelScrolled { wheel, delta, x, y } => {
+                Event::MouseWheelScrolled { wheel, delta, x, y }
+            }
+            Self::MouseButtonPressed { button, x, y } => Event::MouseButtonPressed { button, x, y },
+            Self::MouseButtonReleased { button, x, y } => {
+                Event::MouseButtonReleased { button, x, y }
+            }
+            Self::MouseMoved { x, y } => Event::MouseMoved { x, y },
+            Self::MouseEntered => Event::MouseEntered,
+            Self::MouseLeft => Event::MouseLeft,
+            Self::JoystickButtonPressed { joystickid, button } => {
+                Event::JoystickButtonPressed { joystickid, button }
+            }
+            Self::JoystickButtonReleased { joystickid, button } => {
+                Event::JoystickButtonReleased { joystickid, button }
+            }
+            Self::JoystickMoved {
+                joystickid,
+                axis,
+                position,
+            } => Event::JoystickMoved {
+                joystickid,
+                axis,
+                position,
+            },
+            Self::JoystickConnected { joystickid } => Event::JoystickConnected { joystickid },
+            Self::JoystickDisconnected { joystickid } => Event::JoystickDisconnected { joystickid },
+        }
+    }
+}
+
+/// Information about the session a recording was made in.
+#[derive(Serialize, Deserialize)]
+pub struct RecordingHeader {
+    /// The size of the window when the recording started.
+    pub window_size: Vector2u,
+    /// The player's progress when the recording started.
+    pub save_data: SaveData,
+    pub keymap: Keymap,
+    /// The player's preferences when the recording started.
+    #[serde(default)]
+    pub config: Config,
+}
+
+/// Everything the game received during a single frame.
+#[derive(Serialize, Deserialize, Default)]
+pub struct RecordedFrame {
+    pub delta_time: Duration,
+    pub events: Vec<RecordedEvent>,
+}
+
+/// Writes the frames of a session to a recording file as they happen.
+pub struct Recorder {
+    writer: BufWriter<File>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path, header: &RecordingHeader) -> anyhow::Result<Self> {
+        let mut recorder = Self {
+            writer: BufWriter::new(File::create(path)?),
+        };
+        recorder.write_line(header)?;
+
+        Ok(recorder)
+    }
+
+    /// Appends a frame to the recording.
+    pub fn record_frame(&mut self, frame: &RecordedFrame) -> anyhow::Result<()> {
+        self.write_line(frame)
+    }
+
+    fn write_line(&mut self, value: &impl Serialize) -> anyhow::Result<()> {
+        ron::ser::to_writer(&mut self.writer, value)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Reads back the frames of a recording file.
+pub struct Playback {
+    header: RecordingHeader,
+    lines: Lines<BufReader<File>>,
+}
+
+impl Playback {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let mut lines = BufReader::new(File::open(path)?).lines();
+        let header = match lines.next() {
+            Some(line) => ron::from_str(&line?)?,
+            None => anyhow::bail!("recording is empty"),
+        };
+
+        Ok(Self { header, lines })
+    }
+
+    pub fn header(&self) -> &RecordingHeader {
+        &self.header
+    }
+
+    /// Reads the next frame of the recording, or `None` if it has ended.
+    pub fn next_frame(&mut self) -> anyhow::Result<Option<RecordedFrame>> {
+        match self.lines.next() {
+            Some(line) => Ok(Some(ron::from_str(&line?)?)),
+            None => Ok(None),
+        }
+    }
+}
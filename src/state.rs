@@ -28,8 +28,16 @@ pub trait State<'s> {
 mod level_select;
 pub use level_select::*;
 
+mod menu;
+
+mod paused;
+pub use paused::*;
+
 mod playing;
 pub use playing::*;
 
+mod settings;
+pub use settings::*;
+
 mod transitioning;
 pub use transitioning::*;
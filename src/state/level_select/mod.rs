@@ -1,9 +1,12 @@
-use std::ops::ControlFlow;
+use std::{ops::ControlFlow, time::Duration};
 
 use sfml::{
-    graphics::{BlendMode, FloatRect, Rect, RenderStates, RenderTarget},
-    system::Vector2u,
-    window::{Event, Key},
+    graphics::{
+        BlendMode, FloatRect, Rect, RectangleShape, RenderStates, RenderTarget, Shape, Sprite,
+        Transformable,
+    },
+    system::{Vector2f, Vector2u},
+    window::Event,
 };
 
 use sfml::graphics::Color;
@@ -12,11 +15,14 @@ use sfml::graphics::RenderWindow;
 
 use crate::{
     context::{Context, SaveData},
-    level::camera_transform,
-    ui::{get_ui_obj_from_tiled_obj, update_button, ButtonState, UiObject},
+    input_system::GameAction,
+    level::{camera_transform, Direction},
+    ui::{get_ui_obj_from_tiled_obj, sprite_from_tiled_obj, update_button, ButtonState, UiObject},
 };
 
-use super::{playing::Playing, State, Transitioning};
+use super::{
+    playing::Playing, Easing, Settings, State, Transition, TransitionStyle, Transitioning,
+};
 
 mod ui;
 
@@ -27,12 +33,16 @@ use ui::*;
 pub struct LevelSelect<'s> {
     drawables: Vec<Box<dyn UiObject<'s> + 's>>,
     level_arrays: Vec<LevelArray<'s>>,
+    /// The level array and level button focused through directional input, if any.
+    focus: Option<(usize, usize)>,
+    settings_button: Option<Sprite<'s>>,
 }
 
 impl<'s> LevelSelect<'s> {
     pub fn new(ctx: &Context<'s>) -> anyhow::Result<Self> {
         let mut drawables: Vec<Box<dyn UiObject + 's>> = Vec::new();
         let mut level_arrays = Vec::new();
+        let mut settings_button = None;
         let assets = ctx.assets;
 
         let object_group = assets
@@ -54,7 +64,9 @@ impl<'s> LevelSelect<'s> {
                     .find(|(_, cat)| cat.name == object.user_type)
                     .expect("Unknown level category in level map")
                     .0;
-                level_arrays.push(LevelArray::new(ctx, rect, category));
+                level_arrays.push((rect, LevelArray::new(ctx, rect, category)));
+            } else if object.name == "settings_button" {
+                settings_button = Some(sprite_from_tiled_obj(ctx, &object)?);
             } else if let Ok(obj) = get_ui_obj_from_tiled_obj(ctx, &object) {
                 drawables.push(obj);
             } else {
@@ -62,44 +74,111 @@ impl<'s> LevelSelect<'s> {
             }
         }
 
+        // Sort arrays from top to bottom so they can be navigated through with directional input
+        level_arrays.sort_by(|(a, _), (b, _)| a.top.total_cmp(&b.top));
+        let level_arrays = level_arrays.into_iter().map(|(_, array)| array).collect();
+
         Ok(Self {
             drawables,
             level_arrays,
+            focus: None,
+            settings_button,
         })
     }
+
+    /// Moves the focus to the neighbouring level button in the direction of a movement action.
+    fn move_focus(&mut self, action: GameAction) {
+        let Some((array, level)) = self.focus else {
+            // Focus the first level if nothing was focused yet
+            self.focus = (!self.level_arrays.is_empty()).then_some((0, 0));
+            return;
+        };
+
+        let (array, level) = match action {
+            GameAction::MoveNorth => (array.saturating_sub(1), level),
+            GameAction::MoveSouth => ((array + 1).min(self.level_arrays.len() - 1), level),
+            GameAction::MoveWest => (array, level.saturating_sub(1)),
+            GameAction::MoveEast => (array, level + 1),
+            _ => return,
+        };
+        let level = level.min(self.level_arrays[array].sprites.len().saturating_sub(1));
+
+        self.focus = Some((array, level));
+    }
+
+    /// Transitions to the settings screen, which comes back to the level select once closed.
+    fn open_settings(&self, ctx: &mut Context<'s>) -> ControlFlow<Box<dyn State<'s> + 's>, ()> {
+        ControlFlow::Break(Box::new(
+            Transitioning::new(
+                ctx.assets,
+                Transition::default(),
+                self.clone(),
+                Settings::new(|ctx: &mut Context<'s>| -> Box<dyn State<'s> + 's> {
+                    Box::new(LevelSelect::new(ctx).unwrap())
+                }),
+            )
+            .unwrap(),
+        ))
+    }
+
+    /// Transitions to the level with the given index of the level array given.
+    fn play_level(
+        &self,
+        ctx: &mut Context<'s>,
+        array: usize,
+        level: usize,
+    ) -> ControlFlow<Box<dyn State<'s> + 's>, ()> {
+        ControlFlow::Break(Box::new(
+            Transitioning::new(
+                ctx.assets,
+                Transition::new(
+                    TransitionStyle::Wipe(Direction::East),
+                    Duration::from_millis(350),
+                )
+                .with_easing(Easing::EaseInOut),
+                self.clone(),
+                Playing::new(ctx, level, self.level_arrays[array].category).unwrap(),
+            )
+            .unwrap(),
+        ))
+    }
 }
 
 impl<'s> State<'s> for LevelSelect<'s> {
     fn tick(
         &mut self,
         ctx: &mut Context<'s>,
-        window: &mut RenderWindow,
+        _window: &mut RenderWindow,
     ) -> ControlFlow<Box<dyn State<'s> + 's>, ()> {
+        for action in ctx.input.repeated_actions().to_vec() {
+            self.move_focus(action);
+        }
+
         let mut level_to_transition_to = None;
-        for level_array in self.level_arrays.iter_mut() {
+        for (array_idx, level_array) in self.level_arrays.iter_mut().enumerate() {
             let category = &ctx.assets.level_categories[level_array.category];
 
             for level_idx in 0..category.maps.len() {
                 let level_button = &mut level_array.sprites[level_idx];
                 if level_button.unlocked()
-                    && update_button(ctx, window, &mut level_button.sprite) == ButtonState::Pressed
+                    && update_button(ctx, &mut level_button.sprite) == ButtonState::Pressed
                 {
                     // Lifetime shenanigans: Can't return here because we need access to self, which is currently being mutably borrowed
-                    level_to_transition_to = Some((level_idx, level_array.category));
+                    level_to_transition_to = Some((array_idx, level_idx));
                     break;
                 }
             }
         }
 
-        if let Some((idx, category)) = level_to_transition_to {
-            ControlFlow::Break(Box::new(
-                Transitioning::new(
-                    ctx.assets,
-                    self.clone(),
-                    Playing::new(ctx, idx, category).unwrap(),
-                )
-                .unwrap(),
-            ))
+        let settings_pressed = self
+            .settings_button
+            .as_mut()
+            .is_some_and(|button| update_button(ctx, button) == ButtonState::Pressed);
+
+        if let Some((array, level)) = level_to_transition_to {
+            self.play_level(ctx, array, level)
+        } else if settings_pressed {
+            self.open_settings(ctx)
         } else {
             ControlFlow::Continue(())
         }
@@ -111,6 +190,8 @@ impl<'s> State<'s> for LevelSelect<'s> {
         window: &mut RenderWindow,
         event: Event,
     ) -> ControlFlow<Box<dyn State<'s> + 's>, ()> {
+        let action = ctx.input.action_for_event(&event);
+
         match event {
             Event::Resized { width, height } => {
                 let view = sfml::graphics::View::from_rect(Rect {
@@ -126,11 +207,7 @@ impl<'s> State<'s> for LevelSelect<'s> {
 
             #[cfg(debug_assertions)]
             // Unlock all levels when Ctrl+I is pressed
-            Event::KeyPressed {
-                code: Key::I,
-                ctrl: true,
-                ..
-            } => {
+            _ if action == Some(GameAction::UnlockAllLevels) => {
                 for category in ctx.assets.level_categories.iter() {
                     for level in category.maps.iter() {
                         ctx.completed_levels.complete_lvl(level.1.clone());
@@ -141,17 +218,29 @@ impl<'s> State<'s> for LevelSelect<'s> {
             }
 
             // Reset progress when Ctrl+N is pressed
-            Event::KeyPressed {
-                code: Key::N,
-                ctrl: true,
-                ..
-            } => {
+            _ if action == Some(GameAction::ResetProgress) => {
                 ctx.completed_levels = SaveData::default();
 
                 *self = LevelSelect::new(ctx).unwrap();
             }
 
-            _ => (),
+            _ => match action {
+                Some(
+                    movement @ (GameAction::MoveNorth
+                    | GameAction::MoveSouth
+                    | GameAction::MoveWest
+                    | GameAction::MoveEast),
+                ) => self.move_focus(movement),
+                Some(GameAction::OpenSettings) => return self.open_settings(ctx),
+                Some(GameAction::Confirm) => {
+                    if let Some((array, level)) = self.focus {
+                        if self.level_arrays[array].sprites[level].unlocked() {
+                            return self.play_level(ctx, array, level);
+                        }
+                    }
+                }
+                _ => (),
+            },
         }
 
         ControlFlow::Continue(())
@@ -169,15 +258,20 @@ impl<'s> State<'s> for LevelSelect<'s> {
         let render_states = RenderStates::new(BlendMode::ALPHA, camera_transform, None, None);
 
         target.clear(
-            ctx.assets
-                .main_menu
-                .background_color
-                .map_or(Color::BLACK, |c| Color::rgb(c.red, c.green, c.blue)),
+            ctx.background_color(
+                ctx.assets
+                    .main_menu
+                    .background_color
+                    .map_or(Color::BLACK, |c| Color::rgb(c.red, c.green, c.blue)),
+            ),
         );
 
         for drawable in self.drawables.iter() {
             target.draw_with_renderstates(drawable.as_drawable(), &render_states);
         }
+        if let Some(settings_button) = self.settings_button.as_ref() {
+            target.draw_with_renderstates(settings_button, &render_states);
+        }
 
         for level_array in self.level_arrays.iter() {
             for button in level_array.sprites.iter() {
@@ -185,7 +279,97 @@ impl<'s> State<'s> for LevelSelect<'s> {
                 if let Some(lock) = button.lock_sprite.as_ref() {
                     target.draw_with_renderstates(lock, &render_states);
                 }
+                for star in button.star_sprites.iter() {
+                    target.draw_with_renderstates(star, &render_states);
+                }
             }
         }
+
+        if let Some((array, level)) = self.focus {
+            const FOCUS_OUTLINE_THICKNESS: f32 = 8.;
+            let bounds = self.level_arrays[array].sprites[level]
+                .sprite
+                .global_bounds();
+            let mut focus_outline = RectangleShape::with_size(Vector2f::new(
+                bounds.width - FOCUS_OUTLINE_THICKNESS * 2.,
+                bounds.height - FOCUS_OUTLINE_THICKNESS * 2.,
+            ));
+            focus_outline.set_position(Vector2f::new(
+                bounds.left + FOCUS_OUTLINE_THICKNESS,
+                bounds.top + FOCUS_OUTLINE_THICKNESS,
+            ));
+            focus_outline.set_fill_color(Color::TRANSPARENT);
+            focus_outline.set_outline_color(ctx.theme().accent_color);
+            focus_outline.set_outline_thickness(FOCUS_OUTLINE_THICKNESS);
+            target.draw_with_renderstates(&focus_outline, &render_states);
+        }
+
+        self.draw_thumbnail_preview(ctx, target, &render_states);
+    }
+}
+
+impl LevelSelect<'_> {
+    /// Draws a miniature of the unlocked level being hovered over, or the focused one otherwise,
+    /// next to its button.
+    fn draw_thumbnail_preview(
+        &self,
+        ctx: &Context,
+        target: &mut dyn RenderTarget,
+        render_states: &RenderStates,
+    ) {
+        const PREVIEW_SCALE: f32 = 4.;
+        const PREVIEW_MARGIN: f32 = 16.;
+
+        let hovered = self.level_arrays.iter().enumerate().find_map(|(array, a)| {
+            let level = a
+                .sprites
+                .iter()
+                .position(|b| ctx.input.mouse().is_hovering_ui(b.sprite.global_bounds()))?;
+            Some((array, level))
+        });
+        let Some((array, level)) = hovered.or(self.focus) else {
+            return;
+        };
+        let button = &self.level_arrays[array].sprites[level];
+        if !button.unlocked() {
+            return;
+        }
+
+        let category = &ctx.assets.level_categories[self.level_arrays[array].category];
+        let Some(texture) = ctx.thumbnails.get(&category.maps[level].1) else {
+            return;
+        };
+
+        let bounds = button.sprite.global_bounds();
+        let texture_size = texture.size();
+        let scale = bounds.height * PREVIEW_SCALE / texture_size.x.max(texture_size.y) as f32;
+        let size = Vector2f::new(texture_size.x as f32, texture_size.y as f32) * scale;
+
+        // Show the preview above the button, or below it if there is no room
+        let menu_size = Vector2f::new(
+            (ctx.assets.main_menu.width * ctx.assets.main_menu.tile_width) as f32,
+            (ctx.assets.main_menu.height * ctx.assets.main_menu.tile_height) as f32,
+        );
+        let above = bounds.top - PREVIEW_MARGIN - size.y;
+        let position = Vector2f::new(
+            (bounds.left + (bounds.width - size.x) / 2.).clamp(0., (menu_size.x - size.x).max(0.)),
+            if above >= 0. {
+                above
+            } else {
+                bounds.top + bounds.height + PREVIEW_MARGIN
+            },
+        );
+
+        let mut frame = RectangleShape::with_size(size);
+        frame.set_position(position);
+        frame.set_fill_color(Color::BLACK);
+        frame.set_outline_color(ctx.theme().accent_color);
+        frame.set_outline_thickness(4.);
+        target.draw_with_renderstates(&frame, render_states);
+
+        let mut sprite = Sprite::with_texture(texture);
+        sprite.set_position(position);
+        sprite.set_scale(Vector2f::new(scale, scale));
+        target.draw_with_renderstates(&sprite, render_states);
     }
 }
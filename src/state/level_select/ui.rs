@@ -3,7 +3,7 @@ use sfml::{
     system::Vector2f,
 };
 
-use crate::context::Context;
+use crate::{context::Context, ui::star_rating_sprites};
 
 /// A button representing a playable level. May be locked.
 ///
@@ -12,6 +12,8 @@ use crate::context::Context;
 pub struct LevelArrayButton<'s> {
     pub sprite: Sprite<'s>,
     pub lock_sprite: Option<Sprite<'s>>,
+    /// The best star rating obtained on the level, if it has a par.
+    pub star_sprites: Vec<Sprite<'s>>,
 }
 
 impl LevelArrayButton<'_> {
@@ -39,6 +41,7 @@ impl<'s> LevelArray<'s> {
         let mut level_icon = ctx.assets.icon_tilesheet.tile_sprite(91).unwrap();
         let mut lock_icon = ctx.assets.icon_tilesheet.tile_sprite(115).unwrap();
         let category = &ctx.assets.level_categories[category_idx];
+        let category_color = ctx.category_color(category_idx);
         level_icon.set_position(Vector2f::new(rect.left, rect.top));
         level_icon.set_scale(Vector2f::new(
             rect.height / level_icon.global_bounds().height,
@@ -50,29 +53,41 @@ impl<'s> LevelArray<'s> {
             rect.height / lock_icon.global_bounds().height,
         ));
 
-        let mut completed_previous_level = true;
-        for level in category.maps.iter() {
-            let completed_level = ctx.completed_levels.internal_set().contains(&level.1);
-            let is_unlocked = completed_level || completed_previous_level;
+        for (level_idx, level) in category.maps.iter().enumerate() {
+            let is_unlocked = category.is_unlocked(level_idx, &ctx.completed_levels);
             let color = if is_unlocked {
                 Color {
                     a: 50,
-                    ..category.color
+                    ..category_color
                 }
             } else {
-                category.color
+                category_color
             };
             level_icon.set_color(color);
+
+            let star_sprites = match ctx.completed_levels.stars(&level.1) {
+                Some(stars) => {
+                    let bounds = level_icon.global_bounds();
+                    let star_size = bounds.width / 3.;
+                    star_rating_sprites(
+                        ctx.assets,
+                        stars,
+                        Vector2f::new(bounds.left, bounds.top + bounds.height - star_size),
+                        star_size,
+                    )
+                }
+                None => Vec::new(),
+            };
+
             buttons.push(LevelArrayButton {
                 sprite: level_icon.clone(),
                 lock_sprite: (!is_unlocked).then_some(lock_icon.clone()),
+                star_sprites,
             });
 
             // Move to where the next icon will go
             level_icon.move_(Vector2f::new(level_icon.global_bounds().width, 0.));
             lock_icon.move_(Vector2f::new(level_icon.global_bounds().width, 0.));
-
-            completed_previous_level = completed_level;
         }
 
         Self {
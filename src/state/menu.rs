@@ -0,0 +1,149 @@
+//! A list of text entries shared by the menus built in code, such as the settings screen.
+
+use sfml::{
+    graphics::{
+        Color, FloatRect, RectangleShape, RenderStates, RenderTarget, Shape, Text, Transformable,
+    },
+    system::{Vector2f, Vector2u},
+};
+
+use crate::context::Context;
+
+/// A vertical list of text entries that can be focused with the keyboard, a joystick or the mouse.
+/// It scrolls to keep the focused entry in view when it does not fit the window.
+pub(super) struct MenuList {
+    len: usize,
+    /// The index of the focused entry.
+    focus: usize,
+    /// The index of the first entry shown.
+    scroll: usize,
+}
+
+impl MenuList {
+    const TITLE_SIZE: u32 = 60;
+    const ENTRY_SIZE: u32 = 32;
+    const ENTRY_WIDTH: f32 = 760.;
+    const ENTRY_HEIGHT: f32 = 52.;
+    /// Where the first entry starts, measured from the top of the window.
+    const ENTRIES_TOP: f32 = 140.;
+    /// The space left below the last entry shown.
+    const ENTRIES_BOTTOM_MARGIN: f32 = 20.;
+    const FOCUS_OUTLINE_THICKNESS: f32 = 4.;
+
+    pub fn new(len: usize) -> Self {
+        Self {
+            len,
+            focus: 0,
+            scroll: 0,
+        }
+    }
+
+    pub fn focus(&self) -> usize {
+        self.focus
+    }
+
+    /// Moves the focus by `step` entries, stopping at either end of the list.
+    pub fn move_focus(&mut self, step: isize) {
+        self.focus = self
+            .focus
+            .saturating_add_signed(step)
+            .min(self.len.saturating_sub(1));
+    }
+
+    /// How many entries fit in a window of the given size, which is always at least one.
+    fn visible_count(window_size: Vector2u) -> usize {
+        let height = window_size.y as f32 - Self::ENTRIES_TOP - Self::ENTRIES_BOTTOM_MARGIN;
+        ((height / Self::ENTRY_HEIGHT) as usize).max(1)
+    }
+
+    /// The index of the first entry shown on a window of the given size, such that the focused
+    /// entry is in view.
+    fn first_visible(&self, window_size: Vector2u) -> usize {
+        let visible_count = Self::visible_count(window_size);
+        self.scroll
+            .min(self.len.saturating_sub(visible_count))
+            .clamp((self.focus + 1).saturating_sub(visible_count), self.focus)
+    }
+
+    /// The area of the window taken by the entry with the given index, if it is in view.
+    fn entry_bounds(&self, window_size: Vector2u, idx: usize) -> Option<FloatRect> {
+        let row = idx.checked_sub(self.first_visible(window_size))?;
+        (row < Self::visible_count(window_size)).then(|| {
+            FloatRect::new(
+                (window_size.x as f32 - Self::ENTRY_WIDTH) / 2.,
+                Self::ENTRIES_TOP + Self::ENTRY_HEIGHT * row as f32,
+                Self::ENTRY_WIDTH,
+                Self::ENTRY_HEIGHT,
+            )
+        })
+    }
+
+    /// Scrolls the focused entry into view and focuses the entry under the mouse, if any, as long
+    /// as the mouse was moved or clicked so that it doesn't take focus away from the keyboard.
+    /// Returns the index of the entry clicked this frame, if any.
+    pub fn update(&mut self, ctx: &Context, window_size: Vector2u) -> Option<usize> {
+        self.scroll = self.first_visible(window_size);
+
+        let mouse_position = ctx.input.mouse().position();
+        let mouse_position = Vector2f::new(mouse_position.x as f32, mouse_position.y as f32);
+        let hovered = (0..self.len).find(|idx| {
+            self.entry_bounds(window_size, *idx)
+                .is_some_and(|bounds| bounds.contains(mouse_position))
+        })?;
+        if !ctx.input.mouse().is_in_window() {
+            return None;
+        }
+
+        let mouse = ctx.input.mouse();
+        if mouse.just_moved() || ctx.input.just_pressed_lmb() || ctx.input.just_released_lmb() {
+            self.focus = hovered;
+        }
+        ctx.input.just_released_lmb().then_some(hovered)
+    }
+
+    /// Draws a menu's title centered at the top of the target.
+    pub fn draw_title(ctx: &Context, target: &mut dyn RenderTarget, title: &str) {
+        let mut title = Text::new(title, ctx.font(), Self::TITLE_SIZE);
+        title.set_fill_color(ctx.theme().text_color);
+        title.set_position(Vector2f::new(
+            target.size().x as f32 / 2. - title.global_bounds().width / 2.,
+            40.,
+        ));
+        target.draw_with_renderstates(&title, &RenderStates::DEFAULT);
+    }
+
+    /// Draws the entries in view, which must be as many as the list's length.
+    pub fn draw(&self, ctx: &Context, target: &mut dyn RenderTarget, entries: &[String]) {
+        let theme = ctx.theme();
+
+        for (idx, entry) in entries.iter().enumerate() {
+            let Some(bounds) = self.entry_bounds(target.size(), idx) else {
+                continue;
+            };
+
+            if idx == self.focus {
+                let mut focus_outline = RectangleShape::with_size(Vector2f::new(
+                    bounds.width - Self::FOCUS_OUTLINE_THICKNESS * 2.,
+                    bounds.height - Self::FOCUS_OUTLINE_THICKNESS * 2.,
+                ));
+                focus_outline.set_position(Vector2f::new(
+                    bounds.left + Self::FOCUS_OUTLINE_THICKNESS,
+                    bounds.top + Self::FOCUS_OUTLINE_THICKNESS,
+                ));
+                focus_outline.set_fill_color(Color::TRANSPARENT);
+                focus_outline.set_outline_color(theme.accent_color);
+                focus_outline.set_outline_thickness(Self::FOCUS_OUTLINE_THICKNESS);
+                target.draw_with_renderstates(&focus_outline, &RenderStates::DEFAULT);
+            }
+
+            let mut text = Text::new(entry, ctx.font(), Self::ENTRY_SIZE);
+            text.set_fill_color(theme.text_color);
+            let text_bounds = text.local_bounds();
+            text.set_position(Vector2f::new(
+                bounds.left + (bounds.width - text_bounds.width) / 2. - text_bounds.left,
+                bounds.top + (bounds.height - text_bounds.height) / 2. - text_bounds.top,
+            ));
+            target.draw_with_renderstates(&text, &RenderStates::DEFAULT);
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use std::ops::ControlFlow;
+
+use sfml::{
+    graphics::{Color, RectangleShape, RenderStates, RenderTarget, RenderWindow, Shape},
+    system::Vector2f,
+    window::Event,
+};
+
+use crate::{context::Context, input_system::GameAction};
+
+use super::{menu::MenuList, Playing, Settings, State, Transition, Transitioning};
+
+/// An entry of the pause menu.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PausedEntry {
+    Resume,
+    Restart,
+    Settings,
+    LevelSelect,
+}
+
+impl PausedEntry {
+    const ALL: [PausedEntry; 4] = [
+        PausedEntry::Resume,
+        PausedEntry::Restart,
+        PausedEntry::Settings,
+        PausedEntry::LevelSelect,
+    ];
+
+    fn text(self) -> &'static str {
+        match self {
+            PausedEntry::Resume => "Resume",
+            PausedEntry::Restart => "Restart level",
+            PausedEntry::Settings => "Settings",
+            PausedEntry::LevelSelect => "Back to level select",
+        }
+    }
+}
+
+/// A menu shown over a level being played, which is left as is until the menu is closed.
+pub struct Paused<'s> {
+    /// The level being played, which is only taken once the menu is left.
+    playing: Option<Box<Playing<'s>>>,
+    menu: MenuList,
+}
+
+impl<'s> Paused<'s> {
+    /// The color drawn over the level to make the menu stand out.
+    const DIM_COLOR: Color = Color::rgba(0, 0, 0, 170);
+
+    pub fn new(playing: Playing<'s>) -> Self {
+        Self {
+            playing: Some(Box::new(playing)),
+            menu: MenuList::new(PausedEntry::ALL.len()),
+        }
+    }
+
+    /// Goes back to the level, with any changes made to the config applied.
+    fn resume(&mut self, ctx: &mut Context<'s>) -> ControlFlow<Box<dyn State<'s> + 's>, ()> {
+        match self.playing.take() {
+            Some(mut playing) => {
+                playing.apply_config(ctx);
+                ControlFlow::Break(playing)
+            }
+            None => ControlFlow::Continue(()),
+        }
+    }
+
+    fn open_settings(&mut self, ctx: &mut Context<'s>) -> ControlFlow<Box<dyn State<'s> + 's>, ()> {
+        let Some(playing) = self.playing.take() else {
+            return ControlFlow::Continue(());
+        };
+
+        ControlFlow::Break(Box::new(
+            Transitioning::new(
+                ctx.assets,
+                Transition::default(),
+                Paused::new((*playing).clone()),
+                Settings::new(move |_ctx: &mut Context<'s>| -> Box<dyn State<'s> + 's> {
+                    Box::new(Paused::new(*playing))
+                })
+                .without_theme(),
+            )
+            .unwrap(),
+        ))
+    }
+
+    fn activate_focused(
+        &mut self,
+        ctx: &mut Context<'s>,
+    ) -> ControlFlow<Box<dyn State<'s> + 's>, ()> {
+        match PausedEntry::ALL[self.menu.focus()] {
+            PausedEntry::Resume => self.resume(ctx),
+            PausedEntry::Restart => {
+                if let Some(playing) = self.playing.as_mut() {
+                    playing.restart(ctx);
+                }
+                self.resume(ctx)
+            }
+            PausedEntry::Settings => self.open_settings(ctx),
+            PausedEntry::LevelSelect => match self.playing.as_ref() {
+                Some(playing) => playing.back_to_level_select(ctx, Playing::BACK_TRANSITION),
+                None => ControlFlow::Continue(()),
+            },
+        }
+    }
+
+    fn handle_action(
+        &mut self,
+        ctx: &mut Context<'s>,
+        action: GameAction,
+    ) -> ControlFlow<Box<dyn State<'s> + 's>, ()> {
+        match action {
+            GameAction::MoveNorth => self.menu.move_focus(-1),
+            GameAction::MoveSouth => self.menu.move_focus(1),
+            GameAction::Confirm => return self.activate_focused(ctx),
+            GameAction::Back => return self.resume(ctx),
+            GameAction::OpenSettings => return self.open_settings(ctx),
+            _ => (),
+        }
+
+        ControlFlow::Continue(())
+    }
+}
+
+impl<'s> State<'s> for Paused<'s> {
+    fn tick(
+        &mut self,
+        ctx: &mut Context<'s>,
+        window: &mut RenderWindow,
+    ) -> ControlFlow<Box<dyn State<'s> + 's>, ()> {
+        for action in ctx.input.repeated_actions().to_vec() {
+            self.handle_action(ctx, action)?;
+        }
+
+        if self.menu.update(ctx, window.size()).is_some() {
+            return self.activate_focused(ctx);
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    fn process_event(
+        &mut self,
+        ctx: &mut Context<'s>,
+        window: &mut RenderWindow,
+        event: Event,
+    ) -> ControlFlow<Box<dyn State<'s> + 's>, ()> {
+        if let Event::Resized { width, height } = event {
+            let view = sfml::graphics::View::from_rect(sfml::graphics::Rect {
+                left: 0.,
+                top: 0.,
+                width: width as f32,
+                height: height as f32,
+            });
+            window.set_view(&view);
+        }
+
+        match ctx.input.action_for_event(&event) {
+            Some(action) => self.handle_action(ctx, action),
+            None => ControlFlow::Continue(()),
+        }
+    }
+
+    fn draw(&self, ctx: &mut Context<'s>, target: &mut dyn RenderTarget) {
+        if let Some(playing) = self.playing.as_ref() {
+            playing.draw(ctx, target);
+        }
+
+        let target_size = target.size();
+        let mut dim =
+            RectangleShape::with_size(Vector2f::new(target_size.x as f32, target_size.y as f32));
+        dim.set_fill_color(Self::DIM_COLOR);
+        target.draw_with_renderstates(&dim, &RenderStates::DEFAULT);
+
+        MenuList::draw_title(ctx, target, "Paused");
+        let entries = PausedEntry::ALL.map(|entry| entry.text().to_owned());
+        self.menu.draw(ctx, target, &entries);
+    }
+}
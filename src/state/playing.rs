@@ -2,23 +2,30 @@ use sfml::graphics::Rect;
 
 use sfml;
 
+use sfml::graphics::RectangleShape;
 use sfml::graphics::RenderTarget;
+use sfml::graphics::Shape;
 
 use sfml::graphics::Sprite;
+use sfml::graphics::Transform;
 use sfml::graphics::Transformable;
 
+use sfml::system::Vector2i;
 use sfml::system::Vector2u;
-use sfml::window::Key;
 
-use super::transitioning::Transitioning;
+use super::transitioning::{Easing, Transition, TransitionStyle, Transitioning};
 use super::LevelSelect;
+use super::Paused;
+use super::Settings;
 
 use std;
 
+use sfml::window::mouse;
 use sfml::window::Event;
 
 use sfml::system::Vector2f;
 
+use sfml::graphics::Color;
 use sfml::graphics::Text;
 
 use sfml::graphics::BlendMode;
@@ -26,13 +33,18 @@ use sfml::graphics::BlendMode;
 use sfml::graphics::RenderStates;
 
 use std::ops::ControlFlow;
+use std::time::Duration;
 
 use sfml::graphics::RenderWindow;
 
 use crate::context::Context;
+use crate::input_system::GameAction;
 use crate::level::camera_transform;
+use crate::level::Camera;
+use crate::level::Direction;
 use crate::ui::get_ui_obj_from_tiled_obj;
 use crate::ui::sprite_from_tiled_obj;
+use crate::ui::star_rating_sprites;
 use crate::ui::update_button;
 use crate::ui::ButtonState;
 use crate::ui::UiObject;
@@ -52,6 +64,9 @@ pub struct Playing<'s> {
     level_index: usize,
     category_index: usize,
     level: Level<'s>,
+    camera: Camera,
+    /// Where the cursor was last frame while dragging the view around, if it is being dragged.
+    pan_drag_position: Option<Vector2i>,
     overlay: PlayOverlay<'s>,
 }
 
@@ -71,7 +86,7 @@ impl<'s> Playing<'s> {
             .unwrap();
         for object in object_group.objects() {
             if object.name == "back_button" {
-                let sprite = sprite_from_tiled_obj(ctx.assets, &object)?;
+                let sprite = sprite_from_tiled_obj(ctx, &object)?;
                 back_button = Some(sprite);
             } else if let Ok(obj) = get_ui_obj_from_tiled_obj(ctx, &object) {
                 overlay.push(obj);
@@ -80,13 +95,33 @@ impl<'s> Playing<'s> {
             }
         }
 
+        let level = Level::from_map(
+            &ctx.assets.level_categories[category_index].maps[level_index].0,
+            ctx,
+        )?;
+        // HACK: This should refer to the level tile_width/height, but it refers to the tilesheet tilesize, which might not always coincide
+        let tile_size = Vector2f::new(
+            level.tilesheet().tile_size().x as f32,
+            level.tilesheet().tile_size().y as f32,
+        );
+        let map_size = Vector2f::new(
+            level.tilemap().size().x as f32,
+            level.tilemap().size().y as f32,
+        )
+        .cwise_mul(tile_size);
+        let mut camera = Camera::new(map_size, tile_size, tile_size.y * 2.);
+        camera.set_pixel_perfect(ctx.pixel_perfect(level.tilesheet()));
+        let level_path = &ctx.assets.level_categories[category_index].maps[level_index].1;
+        if let Some(zoom) = ctx.level_zoom.get(level_path) {
+            camera.set_zoom(*zoom);
+        }
+
         Ok(Self {
             level_index,
             category_index,
-            level: Level::from_map(
-                &ctx.assets.level_categories[category_index].maps[level_index].0,
-                ctx,
-            )?,
+            level,
+            camera,
+            pan_drag_position: None,
             overlay: PlayOverlay {
                 overlay,
                 back_button: back_button.expect("found no back button in play overlay"),
@@ -95,6 +130,126 @@ impl<'s> Playing<'s> {
     }
 }
 
+impl<'s> Playing<'s> {
+    /// The transition used when leaving a level without solving it.
+    pub(super) const BACK_TRANSITION: Transition = Transition::new(
+        TransitionStyle::FadeThroughColor(Color::BLACK),
+        Duration::from_millis(400),
+    )
+    .with_easing(Easing::EaseInOut);
+
+    /// The transform used to draw the level onto a target of the given size.
+    fn level_transform(&self, target_size: Vector2u) -> Transform {
+        self.camera.transform(target_size)
+    }
+
+    /// Where the player is drawn on a target of the given size.
+    fn player_screen_position(&self, target_size: Vector2u) -> Vector2f {
+        self.level_transform(target_size)
+            .transform_point(self.level.player_focus())
+    }
+
+    /// Transitions back to the level select screen.
+    pub(super) fn back_to_level_select(
+        &self,
+        ctx: &mut Context<'s>,
+        transition: Transition,
+    ) -> ControlFlow<Box<dyn State<'s> + 's>, ()> {
+        ControlFlow::Break(Box::new(
+            Transitioning::new(
+                ctx.assets,
+                transition,
+                self.clone(),
+                LevelSelect::new(ctx).unwrap(),
+            )
+            .unwrap(),
+        ))
+    }
+
+    /// Starts the level over.
+    pub(super) fn restart(&mut self, ctx: &Context<'s>) {
+        self.level = Level::from_map(
+            &ctx.assets.level_categories[self.category_index].maps[self.level_index].0,
+            ctx,
+        )
+        .unwrap()
+    }
+
+    /// Applies any changes made to the config since the level started, e.g. from the settings
+    /// screen.
+    pub(super) fn apply_config(&mut self, ctx: &Context<'s>) {
+        let pixel_perfect = ctx.pixel_perfect(self.level.tilesheet());
+        self.level.set_pixel_snapping(pixel_perfect);
+        self.camera.set_pixel_perfect(pixel_perfect);
+    }
+
+    /// Keeps the camera's zoom around for the next time this level is played this session.
+    fn remember_zoom(&self, ctx: &mut Context<'s>) {
+        let level_path = &ctx.assets.level_categories[self.category_index].maps[self.level_index].1;
+        ctx.level_zoom
+            .insert(level_path.clone(), self.camera.zoom());
+    }
+
+    /// Zooms and pans the view according to the mouse and the keys held.
+    fn update_view_controls(&mut self, ctx: &mut Context<'s>, window_size: Vector2u) {
+        const WHEEL_ZOOM_FACTOR: f32 = 1.1;
+        const PAN_SPEED: f32 = 800.;
+
+        let mouse = ctx.input.mouse();
+        let cursor = Vector2f::new(mouse.position().x as f32, mouse.position().y as f32);
+
+        if mouse.wheel_delta() != 0. {
+            self.camera.zoom_at(
+                WHEEL_ZOOM_FACTOR.powf(mouse.wheel_delta()),
+                window_size,
+                cursor,
+            );
+            self.remember_zoom(ctx);
+        }
+
+        // Drag the view around with the right or middle mouse buttons
+        let mouse = ctx.input.mouse();
+        match mouse
+            .drag(mouse::Button::Right)
+            .or_else(|| mouse.drag(mouse::Button::Middle))
+        {
+            Some(drag) => {
+                let from = self.pan_drag_position.unwrap_or(drag.start);
+                self.camera.pan_by(
+                    window_size,
+                    Vector2f::new(from.x as f32, from.y as f32),
+                    Vector2f::new(drag.end.x as f32, drag.end.y as f32),
+                );
+                self.pan_drag_position = Some(drag.end);
+            }
+            None => self.pan_drag_position = None,
+        }
+
+        let pan_direction = [
+            (GameAction::PanNorth, Vector2f::new(0., -1.)),
+            (GameAction::PanSouth, Vector2f::new(0., 1.)),
+            (GameAction::PanWest, Vector2f::new(-1., 0.)),
+            (GameAction::PanEast, Vector2f::new(1., 0.)),
+        ]
+        .into_iter()
+        .filter(|(action, _)| ctx.input.is_action_held(*action))
+        .fold(Vector2f::default(), |sum, (_, direction)| sum + direction);
+        if pan_direction != Vector2f::default() {
+            let window_center = Vector2f::new(window_size.x as f32, window_size.y as f32) / 2.;
+            let offset = pan_direction * PAN_SPEED * ctx.delta_time.as_secs_f32();
+            self.camera
+                .pan_by(window_size, window_center, window_center - offset);
+        }
+    }
+
+    /// Zooms the view in or out around the center of the window.
+    fn zoom_view(&mut self, ctx: &mut Context<'s>, window_size: Vector2u, factor: f32) {
+        let window_center = Vector2f::new(window_size.x as f32, window_size.y as f32) / 2.;
+        self.camera.zoom_at(factor, window_size, window_center);
+        self.remember_zoom(ctx);
+    }
+}
+
 impl<'s> State<'s> for Playing<'s> {
     fn tick(
         &mut self,
@@ -102,15 +257,21 @@ impl<'s> State<'s> for Playing<'s> {
         window: &mut RenderWindow,
     ) -> ControlFlow<Box<dyn State<'s> + 's>, ()> {
         self.level.update(ctx, ctx.delta_time);
-
-        match update_button(ctx, window, &mut self.overlay.back_button) {
-            ButtonState::Pressed => {
-                let next_state =
-                    Transitioning::new(ctx.assets, self.clone(), LevelSelect::new(ctx).unwrap())
-                        .unwrap();
-
-                ControlFlow::Break(Box::new(next_state))
+        self.camera
+            .update(ctx.delta_time, window.size(), self.level.player_focus());
+        self.update_view_controls(ctx, window.size());
+        ctx.input
+            .mouse_mut()
+            .set_world_transform(self.level_transform(window.size()));
+
+        if !self.level.is_won() {
+            for action in ctx.input.repeated_actions().to_vec() {
+                self.level.handle_action(ctx, action);
             }
+        }
+
+        match update_button(ctx, &mut self.overlay.back_button) {
+            ButtonState::Pressed => self.back_to_level_select(ctx, Self::BACK_TRANSITION),
             _ => ControlFlow::Continue(()),
         }
     }
@@ -121,16 +282,22 @@ impl<'s> State<'s> for Playing<'s> {
         window: &mut RenderWindow,
         event: Event,
     ) -> ControlFlow<Box<(dyn State<'s> + 's)>> {
-        let is_level_won = self.level.is_won();
+        const KEY_ZOOM_FACTOR: f32 = 1.25;
+
+        let is_level_won = self.level.is_won() && !self.level.is_failed();
+        let action = ctx.input.action_for_event(&event);
 
         match event {
-            Event::KeyPressed { .. } if is_level_won => {
+            Event::KeyPressed { .. } | Event::JoystickButtonPressed { .. } if is_level_won => {
                 // Mark this level as complete
-                ctx.completed_levels.complete_lvl(
-                    ctx.assets.level_categories[self.category_index].maps[self.level_index]
-                        .1
-                        .clone(),
-                );
+                let level_path = ctx.assets.level_categories[self.category_index].maps
+                    [self.level_index]
+                    .1
+                    .clone();
+                if let Some(stars) = self.level.star_rating() {
+                    ctx.completed_levels.record_stars(level_path.clone(), stars);
+                }
+                ctx.completed_levels.complete_lvl(level_path);
 
                 let next_level_index = self.level_index + 1;
 
@@ -138,19 +305,26 @@ impl<'s> State<'s> for Playing<'s> {
                     >= ctx.assets.level_categories[self.category_index].maps.len()
                 {
                     // Go back to level select if category or game is finished
-                    return ControlFlow::Break(Box::new(
-                        Transitioning::new(
-                            ctx.assets,
-                            self.clone(),
-                            LevelSelect::new(ctx).unwrap(),
+                    return self.back_to_level_select(
+                        ctx,
+                        Transition::new(
+                            TransitionStyle::Slide(Direction::South),
+                            Duration::from_millis(500),
                         )
-                        .unwrap(),
-                    ));
+                        .with_easing(Easing::EaseInOut),
+                    );
                 } else {
                     // Go to next level
                     return ControlFlow::Break(Box::new(
                         Transitioning::new(
                             ctx.assets,
+                            Transition::new(
+                                TransitionStyle::Iris {
+                                    center: self.player_screen_position(window.size()),
+                                },
+                                Duration::from_millis(600),
+                            )
+                            .with_easing(Easing::EaseIn),
                             self.clone(),
                             Playing::new(ctx, next_level_index, self.category_index).unwrap(),
                         )
@@ -158,20 +332,35 @@ impl<'s> State<'s> for Playing<'s> {
                     ));
                 }
             }
-            Event::KeyPressed {
-                code: Key::Escape, ..
-            } => {
+            _ if action == Some(GameAction::Back) => {
+                return ControlFlow::Break(Box::new(Paused::new(self.clone())));
+            }
+            _ if action == Some(GameAction::OpenSettings) => {
+                let mut playing = self.clone();
                 return ControlFlow::Break(Box::new(
-                    Transitioning::new(ctx.assets, self.clone(), LevelSelect::new(ctx).unwrap())
-                        .unwrap(),
+                    Transitioning::new(
+                        ctx.assets,
+                        Transition::default(),
+                        self.clone(),
+                        Settings::new(move |ctx: &mut Context<'s>| -> Box<dyn State<'s> + 's> {
+                            playing.apply_config(ctx);
+                            Box::new(playing)
+                        })
+                        .without_theme(),
+                    )
+                    .unwrap(),
                 ));
             }
-            Event::KeyPressed { code: Key::R, .. } => {
-                self.level = Level::from_map(
-                    &ctx.assets.level_categories[self.category_index].maps[self.level_index].0,
-                    ctx,
-                )
-                .unwrap()
+            _ if action == Some(GameAction::Restart) => self.restart(ctx),
+            _ if action == Some(GameAction::ZoomIn) => {
+                self.zoom_view(ctx, window.size(), KEY_ZOOM_FACTOR)
+            }
+            _ if action == Some(GameAction::ZoomOut) => {
+                self.zoom_view(ctx, window.size(), 1. / KEY_ZOOM_FACTOR)
+            }
+            _ if action == Some(GameAction::ResetView) => {
+                self.camera.reset_view();
+                self.remember_zoom(ctx);
             }
             Event::Resized { width, height } => {
                 let view = sfml::graphics::View::from_rect(Rect {
@@ -182,6 +371,20 @@ impl<'s> State<'s> for Playing<'s> {
                 });
                 window.set_view(&view);
             }
+            Event::MouseButtonPressed {
+                button: mouse::Button::Left,
+                ..
+            } if !is_level_won && !self.level.is_failed() => {
+                // Walk to or interact with the clicked cell, unless the click is meant for the
+                // back button, which is handled while ticking
+                let mouse = ctx.input.mouse();
+                let cell = self.level.cell_at(mouse.world_position());
+                let is_on_back_button =
+                    mouse.is_hovering_ui(self.overlay.back_button.global_bounds());
+                if !is_on_back_button && self.level.tilemap().get_tile(cell).is_some() {
+                    self.level.handle_click(cell);
+                }
+            }
             _ => self.level.handle_event(ctx, event),
         }
 
@@ -189,20 +392,21 @@ impl<'s> State<'s> for Playing<'s> {
     }
 
     fn draw(&self, ctx: &mut Context<'s>, target: &mut dyn RenderTarget) {
-        let is_level_won = self.level.is_won();
+        let is_level_won = self.level.is_won() && !self.level.is_failed();
 
-        let transform = camera_transform(
-            target.size(),
-            Vector2u::new(
-                // HACK: This should refer to the level tile_width/height, but it refers to the tilesheet tilesize, which might not always coincide
-                self.level.tilemap().size().x * self.level.tilesheet().tile_size().x,
-                self.level.tilemap().size().y * self.level.tilesheet().tile_size().y,
-            ),
-            self.level.tilesheet().tile_size().y as f32 * 2.,
-        );
+        let transform = self.level_transform(target.size());
         let render_states = RenderStates::new(BlendMode::ALPHA, transform, None, None);
 
-        target.clear(self.level.background_color);
+        match self.camera.letterbox(target.size()) {
+            Some(area) => {
+                target.clear(ctx.theme().letterbox_color);
+                let mut backdrop = RectangleShape::with_size(area.size());
+                backdrop.set_position(area.position());
+                backdrop.set_fill_color(self.level.background_color);
+                target.draw_with_renderstates(&backdrop, &RenderStates::DEFAULT);
+            }
+            None => target.clear(self.level.background_color),
+        }
 
         target.draw_with_renderstates(&self.level, &render_states);
 
@@ -214,30 +418,94 @@ impl<'s> State<'s> for Playing<'s> {
             } else {
                 "Level complete!"
             };
-            let mut text = Text::new(text, &ctx.assets.win_font, 60);
+            let mut text = Text::new(text, ctx.font(), 60);
+            text.set_fill_color(ctx.theme().text_color);
             text.set_position(Vector2f::new(
                 target.size().x as f32 / 2. - text.global_bounds().width / 2.,
                 10.,
             ));
             target.draw_with_renderstates(&text, &RenderStates::DEFAULT);
 
-            let mut moves_text = Text::new(
-                format!("Used {} moves", self.level.action_count()).as_str(),
-                &ctx.assets.win_font,
-                30,
-            );
+            let moves_text = if self.level.pulling_enabled() {
+                let pull_count = self.level.pull_count();
+                format!(
+                    "Used {} moves and {} pulls",
+                    self.level.action_count() - pull_count,
+                    pull_count
+                )
+            } else {
+                format!("Used {} moves", self.level.action_count())
+            };
+            let mut moves_text = Text::new(moves_text.as_str(), ctx.font(), 30);
+            moves_text.set_fill_color(ctx.theme().text_color);
             moves_text.set_position(Vector2f::new(
                 target.size().x as f32 / 2. - moves_text.global_bounds().width / 2.,
                 text.position().y + text.global_bounds().height + 20.,
             ));
             target.draw_with_renderstates(&moves_text, &RenderStates::DEFAULT);
 
-            let mut subtext = Text::new("Press any key to continue", &ctx.assets.win_font, 30);
+            let mut subtext_y = moves_text.position().y + moves_text.global_bounds().height + 20.;
+
+            if let Some(stars) = self.level.star_rating() {
+                const STAR_SIZE: f32 = 50.;
+                let star_sprites = star_rating_sprites(
+                    ctx.assets,
+                    stars,
+                    Vector2f::new(target.size().x as f32 / 2. - STAR_SIZE * 1.5, subtext_y),
+                    STAR_SIZE,
+                );
+                for star in star_sprites.iter() {
+                    target.draw_with_renderstates(star, &RenderStates::DEFAULT);
+                }
+                subtext_y += STAR_SIZE + 20.;
+            }
+
+            let mut subtext = Text::new("Press any key to continue", ctx.font(), 30);
+            subtext.set_fill_color(ctx.theme().text_color);
             subtext.set_position(Vector2f::new(
                 target.size().x as f32 / 2. - subtext.global_bounds().width / 2.,
-                moves_text.position().y + moves_text.global_bounds().height + 20.,
+                subtext_y,
             ));
             target.draw_with_renderstates(&subtext, &RenderStates::DEFAULT);
+        } else if self.level.is_failed() {
+            let mut text = Text::new("Out of moves!", ctx.font(), 60);
+            text.set_fill_color(ctx.theme().text_color);
+            text.set_position(Vector2f::new(
+                target.size().x as f32 / 2. - text.global_bounds().width / 2.,
+                10.,
+            ));
+            target.draw_with_renderstates(&text, &RenderStates::DEFAULT);
+
+            let keymap = ctx.input.keymap();
+            let hint = format!(
+                "Press {} to undo or {} to restart",
+                keymap.bindings_text(GameAction::Undo, "/"),
+                keymap.bindings_text(GameAction::Restart, "/")
+            );
+            let mut subtext = Text::new(&hint, ctx.font(), 30);
+            subtext.set_fill_color(ctx.theme().text_color);
+            subtext.set_position(Vector2f::new(
+                target.size().x as f32 / 2. - subtext.global_bounds().width / 2.,
+                text.position().y + text.global_bounds().height + 20.,
+            ));
+            target.draw_with_renderstates(&subtext, &RenderStates::DEFAULT);
+        }
+
+        if self.level.par_moves().is_some() || self.level.max_moves().is_some() {
+            let mut counter_text = format!("Moves: {}", self.level.action_count());
+            if let Some(max_moves) = self.level.max_moves() {
+                counter_text.push_str(&format!("/{}", max_moves));
+            }
+            if let Some(par_moves) = self.level.par_moves() {
+                counter_text.push_str(&format!(" (par {})", par_moves));
+            }
+            let mut counter_text = Text::new(&counter_text, ctx.font(), 30);
+            counter_text.set_fill_color(ctx.theme().text_color);
+            counter_text.set_position(Vector2f::new(
+                10.,
+                target.size().y as f32 - counter_text.global_bounds().height - 20.,
+            ));
+            target.draw_with_renderstates(&counter_text, &RenderStates::DEFAULT);
         }
 
         let transform = camera_transform(
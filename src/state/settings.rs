@@ -0,0 +1,440 @@
+use std::ops::ControlFlow;
+
+use sfml::{
+    audio::{Sound, SoundBuffer, SoundSource},
+    graphics::{Color, RenderTarget, RenderWindow},
+    window::{Event, Key},
+};
+
+use crate::{
+    context::Context,
+    input_system::{GameAction, KeyBinding},
+};
+
+use super::{menu::MenuList, State};
+
+/// Creates the state to return to once the settings screen is closed, so that it is set up with
+/// any changes made.
+type BackTo<'s> = Box<dyn FnOnce(&mut Context<'s>) -> Box<dyn State<'s> + 's> + 's>;
+
+/// The actions that can be rebound from the settings screen, in the order they are listed.
+const REBINDABLE_ACTIONS: [GameAction; 17] = [
+    GameAction::MoveNorth,
+    GameAction::MoveSouth,
+    GameAction::MoveWest,
+    GameAction::MoveEast,
+    GameAction::Pull,
+    GameAction::Undo,
+    GameAction::Restart,
+    GameAction::Back,
+    GameAction::Confirm,
+    GameAction::ZoomIn,
+    GameAction::ZoomOut,
+    GameAction::PanNorth,
+    GameAction::PanSouth,
+    GameAction::PanWest,
+    GameAction::PanEast,
+    GameAction::ResetView,
+    GameAction::OpenSettings,
+];
+
+const VOLUMES: [f32; 11] = [0., 10., 20., 30., 40., 50., 60., 70., 80., 90., 100.];
+/// The frame caps to choose from, where 0 means no cap.
+const FRAME_CAPS: [u32; 6] = [0, 30, 60, 120, 144, 240];
+const ANIMATION_SPEEDS: [f32; 6] = [0.5, 0.75, 1., 1.5, 2., 3.];
+/// The pixel-perfect modes to cycle through, where `None` follows the tilesheet.
+const PIXEL_PERFECT_MODES: [Option<bool>; 3] = [None, Some(true), Some(false)];
+
+/// Steps `step` options away from the option closest to `current`, stopping at either end.
+fn step_option<T: Copy + Into<f64>>(options: &[T], current: T, step: isize) -> T {
+    let distance = |option: &T| ((*option).into() - current.into()).abs();
+    let closest = (0..options.len())
+        .min_by(|a, b| distance(&options[*a]).total_cmp(&distance(&options[*b])))
+        .expect("there are no options to choose from");
+
+    options[closest.saturating_add_signed(step).min(options.len() - 1)]
+}
+
+fn on_off(val: bool) -> &'static str {
+    if val {
+        "On"
+    } else {
+        "Off"
+    }
+}
+
+fn action_name(action: GameAction) -> &'static str {
+    match action {
+        GameAction::MoveNorth => "Move up",
+        GameAction::MoveSouth => "Move down",
+        GameAction::MoveWest => "Move left",
+        GameAction::MoveEast => "Move right",
+        GameAction::Pull => "Pull",
+        GameAction::Undo => "Undo",
+        GameAction::Restart => "Restart",
+        GameAction::Back => "Back",
+        GameAction::Confirm => "Confirm",
+        GameAction::ZoomIn => "Zoom in",
+        GameAction::ZoomOut => "Zoom out",
+        GameAction::PanNorth => "Pan up",
+        GameAction::PanSouth => "Pan down",
+        GameAction::PanWest => "Pan left",
+        GameAction::PanEast => "Pan right",
+        GameAction::ResetView => "Reset view",
+        GameAction::OpenSettings => "Settings",
+        GameAction::UnlockAllLevels => "Unlock all levels",
+        GameAction::ResetProgress => "Reset progress",
+    }
+}
+
+/// An entry of the settings screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SettingsEntry {
+    Theme,
+    SoundVolume,
+    UiVolume,
+    Fullscreen,
+    VSync,
+    FrameCap,
+    AnimationSpeed,
+    PixelPerfect,
+    /// Opens the page with the bindings of each action.
+    Controls,
+    Binding(GameAction),
+    ResetControls,
+    Back,
+}
+
+impl SettingsEntry {
+    fn text(self, ctx: &Context) -> String {
+        let config = &ctx.config;
+        match self {
+            SettingsEntry::Theme => format!("Theme: < {} >", ctx.theme().name),
+            SettingsEntry::SoundVolume => format!("Sound volume: < {}% >", config.sound_volume),
+            SettingsEntry::UiVolume => format!("Menu volume: < {}% >", config.ui_volume),
+            SettingsEntry::Fullscreen => format!("Fullscreen: < {} >", on_off(config.fullscreen)),
+            SettingsEntry::VSync => format!("Vertical sync: < {} >", on_off(config.vsync)),
+            SettingsEntry::FrameCap => match config.frame_cap {
+                0 => "Frame cap: < None >".to_owned(),
+                frame_cap => format!("Frame cap: < {} FPS >", frame_cap),
+            },
+            SettingsEntry::AnimationSpeed => {
+                format!("Animation speed: < {}x >", config.animation_speed)
+            }
+            SettingsEntry::PixelPerfect => match config.pixel_perfect {
+                None => "Pixel-perfect: < Auto >".to_owned(),
+                Some(pixel_perfect) => format!("Pixel-perfect: < {} >", on_off(pixel_perfect)),
+            },
+            SettingsEntry::Controls => "Controls".to_owned(),
+            SettingsEntry::Binding(action) => format!(
+                "{}: {}",
+                action_name(action),
+                ctx.input.keymap().bindings_text(action, ", ")
+            ),
+            SettingsEntry::ResetControls => "Reset controls".to_owned(),
+            SettingsEntry::Back => "Back".to_owned(),
+        }
+    }
+}
+
+/// The pages of the settings screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Page {
+    General,
+    Controls,
+}
+
+/// A screen for changing the player's preferences, which are saved to the config files as soon as
+/// they change.
+pub struct Settings<'s> {
+    back_to: Option<BackTo<'s>>,
+    /// Whether the theme can be changed, which is not the case while a level is being played.
+    theme_changeable: bool,
+    page: Page,
+    menu: MenuList,
+    /// The action being rebound, waiting for the player to press a key or button.
+    rebinding: Option<GameAction>,
+}
+
+impl<'s> Settings<'s> {
+    /// Creates a settings screen that goes back to the state created by `back_to` once closed.
+    pub fn new(back_to: impl FnOnce(&mut Context<'s>) -> Box<dyn State<'s> + 's> + 's) -> Self {
+        let mut settings = Self {
+            back_to: Some(Box::new(back_to)),
+            theme_changeable: true,
+            page: Page::General,
+            menu: MenuList::new(0),
+            rebinding: None,
+        };
+        settings.set_page(Page::General);
+        settings
+    }
+
+    /// Hides the theme setting, for when the state to go back to can't change its theme, like a
+    /// level being played.
+    pub fn without_theme(mut self) -> Self {
+        self.theme_changeable = false;
+        self.set_page(self.page);
+        self
+    }
+
+    /// The entries of the current page, in order.
+    fn entries(&self) -> Vec<SettingsEntry> {
+        match self.page {
+            Page::General => {
+                let mut entries = vec![
+                    SettingsEntry::SoundVolume,
+                    SettingsEntry::UiVolume,
+                    SettingsEntry::Fullscreen,
+                    SettingsEntry::VSync,
+                    SettingsEntry::FrameCap,
+                    SettingsEntry::AnimationSpeed,
+                    SettingsEntry::PixelPerfect,
+                    SettingsEntry::Controls,
+                    SettingsEntry::Back,
+                ];
+                if self.theme_changeable {
+                    entries.insert(0, SettingsEntry::Theme);
+                }
+                entries
+            }
+            Page::Controls => REBINDABLE_ACTIONS
+                .into_iter()
+                .map(SettingsEntry::Binding)
+                .chain([SettingsEntry::ResetControls, SettingsEntry::Back])
+                .collect(),
+        }
+    }
+
+    fn set_page(&mut self, page: Page) {
+        self.page = page;
+        self.menu = MenuList::new(self.entries().len());
+    }
+
+    /// Goes back to the general page, or closes the settings screen if already there.
+    fn back(&mut self, ctx: &mut Context<'s>) -> ControlFlow<Box<dyn State<'s> + 's>, ()> {
+        match self.page {
+            Page::General => match self.back_to.take() {
+                Some(back_to) => ControlFlow::Break(back_to(ctx)),
+                None => ControlFlow::Continue(()),
+            },
+            Page::Controls => {
+                self.set_page(Page::General);
+                let controls_idx = self
+                    .entries()
+                    .iter()
+                    .position(|e| *e == SettingsEntry::Controls)
+                    .unwrap_or(0);
+                self.menu.move_focus(controls_idx as isize);
+                ControlFlow::Continue(())
+            }
+        }
+    }
+
+    /// Plays a sound at the given volume, so that the player can hear how loud it is.
+    fn play_sample(ctx: &mut Context<'s>, buffer: &'s SoundBuffer, volume: f32) {
+        let mut sound = Sound::with_buffer(buffer);
+        sound.set_volume(volume);
+        sound.play();
+        ctx.sound.add_sound(sound);
+    }
+
+    /// Changes the value of the focused entry to the previous or next one, depending on the sign
+    /// of `step`, or activates it if it has no value and `step` is positive.
+    fn change_focused(
+        &mut self,
+        ctx: &mut Context<'s>,
+        window: &mut RenderWindow,
+        step: isize,
+    ) -> ControlFlow<Box<dyn State<'s> + 's>, ()> {
+        let assets = ctx.assets;
+        let config = &mut ctx.config;
+        match self.entries()[self.menu.focus()] {
+            SettingsEntry::Theme => {
+                let theme_count = assets.themes.len() as isize;
+                let theme = (ctx.theme_index() as isize + step).rem_euclid(theme_count);
+                // Saves the config by itself
+                ctx.set_theme(theme as usize);
+                return ControlFlow::Continue(());
+            }
+            SettingsEntry::SoundVolume => {
+                config.sound_volume = step_option(&VOLUMES, config.sound_volume, step);
+                let volume = config.sound_volume;
+                Self::play_sample(ctx, &assets.walk_sounds[0], volume);
+            }
+            SettingsEntry::UiVolume => {
+                config.ui_volume = step_option(&VOLUMES, config.ui_volume, step);
+                let volume = config.ui_volume;
+                Self::play_sample(ctx, &assets.ui_click_sound, volume);
+            }
+            SettingsEntry::Fullscreen => {
+                config.fullscreen = !config.fullscreen;
+                crate::recreate_window(window, config);
+            }
+            SettingsEntry::VSync => {
+                config.vsync = !config.vsync;
+                crate::apply_window_config(window, config);
+            }
+            SettingsEntry::FrameCap => {
+                config.frame_cap = step_option(&FRAME_CAPS, config.frame_cap, step);
+                crate::apply_window_config(window, config);
+            }
+            SettingsEntry::AnimationSpeed => {
+                config.animation_speed =
+                    step_option(&ANIMATION_SPEEDS, config.animation_speed, step);
+            }
+            SettingsEntry::PixelPerfect => {
+                let mode = PIXEL_PERFECT_MODES
+                    .iter()
+                    .position(|mode| *mode == config.pixel_perfect)
+                    .unwrap_or(0) as isize;
+                let mode = (mode + step).rem_euclid(PIXEL_PERFECT_MODES.len() as isize);
+                config.pixel_perfect = PIXEL_PERFECT_MODES[mode as usize];
+            }
+            _ if step < 0 => return ControlFlow::Continue(()),
+            SettingsEntry::Controls => {
+                self.set_page(Page::Controls);
+                return ControlFlow::Continue(());
+            }
+            SettingsEntry::Binding(action) => {
+                self.rebinding = Some(action);
+                return ControlFlow::Continue(());
+            }
+            SettingsEntry::ResetControls => {
+                ctx.input.keymap_mut().reset_bindings();
+                ctx.input.keymap().save();
+                return ControlFlow::Continue(());
+            }
+            SettingsEntry::Back => return self.back(ctx),
+        }
+
+        ctx.config.save();
+        ControlFlow::Continue(())
+    }
+
+    /// Binds the key or joystick button pressed to the action being rebound, or cancels rebinding
+    /// if Escape was pressed.
+    fn rebind(&mut self, ctx: &mut Context<'s>, action: GameAction, event: Event) {
+        match event {
+            Event::KeyPressed {
+                code: Key::Escape, ..
+            } => (),
+            // Wait for the key modifiers are held for
+            Event::KeyPressed {
+                code: Key::LControl | Key::RControl,
+                ..
+            } => return,
+            Event::KeyPressed { code, ctrl, .. } => {
+                ctx.input
+                    .keymap_mut()
+                    .set_primary_binding(action, KeyBinding { key: code, ctrl });
+                ctx.input.keymap().save();
+            }
+            Event::JoystickButtonPressed { button, .. } => {
+                ctx.input.keymap_mut().set_primary_button(action, button);
+                ctx.input.keymap().save();
+            }
+            _ => return,
+        }
+
+        self.rebinding = None;
+    }
+
+    fn handle_action(
+        &mut self,
+        ctx: &mut Context<'s>,
+        window: &mut RenderWindow,
+        action: GameAction,
+    ) -> ControlFlow<Box<dyn State<'s> + 's>, ()> {
+        match action {
+            GameAction::MoveNorth => self.menu.move_focus(-1),
+            GameAction::MoveSouth => self.menu.move_focus(1),
+            GameAction::MoveWest => return self.change_focused(ctx, window, -1),
+            GameAction::MoveEast | GameAction::Confirm => {
+                return self.change_focused(ctx, window, 1)
+            }
+            GameAction::Back => return self.back(ctx),
+            _ => (),
+        }
+
+        ControlFlow::Continue(())
+    }
+}
+
+impl<'s> State<'s> for Settings<'s> {
+    fn tick(
+        &mut self,
+        ctx: &mut Context<'s>,
+        window: &mut RenderWindow,
+    ) -> ControlFlow<Box<dyn State<'s> + 's>, ()> {
+        if self.rebinding.is_some() {
+            return ControlFlow::Continue(());
+        }
+
+        for action in ctx.input.repeated_actions().to_vec() {
+            self.handle_action(ctx, window, action)?;
+        }
+
+        if self.menu.update(ctx, window.size()).is_some() {
+            return self.change_focused(ctx, window, 1);
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    fn process_event(
+        &mut self,
+        ctx: &mut Context<'s>,
+        window: &mut RenderWindow,
+        event: Event,
+    ) -> ControlFlow<Box<dyn State<'s> + 's>, ()> {
+        if let Event::Resized { width, height } = event {
+            let view = sfml::graphics::View::from_rect(sfml::graphics::Rect {
+                left: 0.,
+                top: 0.,
+                width: width as f32,
+                height: height as f32,
+            });
+            window.set_view(&view);
+        }
+
+        if let Some(action) = self.rebinding {
+            self.rebind(ctx, action, event);
+            return ControlFlow::Continue(());
+        }
+
+        match ctx.input.action_for_event(&event) {
+            Some(action) => self.handle_action(ctx, window, action),
+            None => ControlFlow::Continue(()),
+        }
+    }
+
+    fn draw(&self, ctx: &mut Context<'s>, target: &mut dyn RenderTarget) {
+        target.clear(
+            ctx.background_color(
+                ctx.assets
+                    .main_menu
+                    .background_color
+                    .map_or(Color::BLACK, |c| Color::rgb(c.red, c.green, c.blue)),
+            ),
+        );
+
+        let title = match self.page {
+            Page::General => "Settings",
+            Page::Controls => "Controls",
+        };
+        MenuList::draw_title(ctx, target, title);
+
+        let entries = self
+            .entries()
+            .into_iter()
+            .map(|entry| match self.rebinding {
+                Some(action) if entry == SettingsEntry::Binding(action) => {
+                    format!("{}: press a key or button, or Escape", action_name(action))
+                }
+                _ => entry.text(ctx),
+            })
+            .collect::<Vec<_>>();
+        self.menu.draw(ctx, target, &entries);
+    }
+}
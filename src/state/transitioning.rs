@@ -1,39 +1,153 @@
+use sfml::graphics::CircleShape;
 use sfml::graphics::Color;
-use sfml::graphics::Rect;
+use sfml::graphics::FloatRect;
+use sfml::graphics::IntRect;
+use sfml::graphics::RectangleShape;
 use sfml::graphics::RenderTarget;
 use sfml::graphics::RenderTexture;
 use sfml::graphics::RenderWindow;
+use sfml::graphics::Shape;
 use sfml::graphics::Sprite;
+use sfml::graphics::Transformable;
+use sfml::system::Vector2f;
+use sfml::system::Vector2i;
+use sfml::system::Vector2u;
 use sfml::window::Event;
 
+use std::cell::RefCell;
 use std::ops::ControlFlow;
 use std::time::Duration;
 
 use crate::assets::AssetManager;
 use crate::context::Context;
+use crate::level::Direction;
 
 use super::State;
 
+/// How progress through a transition is spread over its duration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    /// Starts slow and speeds up.
+    EaseIn,
+    /// Starts fast and slows down.
+    EaseOut,
+    /// Starts and ends slow.
+    EaseInOut,
+}
+
+impl Easing {
+    /// Maps linear progress, from 0 to 1, to eased progress.
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => 1. - (1. - t) * (1. - t),
+            Easing::EaseInOut => t * t * (3. - 2. * t),
+        }
+    }
+}
+
+/// The way the next state replaces the previous one on screen.
+#[derive(Clone, Copy)]
+pub enum TransitionStyle {
+    /// The next state fades in over the previous one.
+    Crossfade,
+    /// The previous state fades out to a color, which then fades out to the next state.
+    FadeThroughColor(Color),
+    /// The previous state is pushed out of the screen towards a direction by the next one.
+    Slide(Direction),
+    /// The next state is uncovered by an edge sweeping over the screen towards a direction.
+    Wipe(Direction),
+    /// The next state is uncovered by a circle growing from a point of the window, e.g. where the
+    /// player is.
+    Iris { center: Vector2f },
+}
+
+/// Describes how to transition between two states.
+#[derive(Clone, Copy)]
+pub struct Transition {
+    pub style: TransitionStyle,
+    pub duration: Duration,
+    pub easing: Easing,
+}
+
+impl Transition {
+    pub const fn new(style: TransitionStyle, duration: Duration) -> Self {
+        Self {
+            style,
+            duration,
+            easing: Easing::Linear,
+        }
+    }
+
+    pub const fn with_easing(self, easing: Easing) -> Self {
+        Self { easing, ..self }
+    }
+}
+
+impl Default for Transition {
+    fn default() -> Self {
+        Self::new(TransitionStyle::Crossfade, Duration::from_millis(200))
+    }
+}
+
 pub struct Transitioning<'s> {
     prev_state: Box<dyn State<'s> + 's>,
     // HACK: This is an option because `tick` does not move the state and as such we cannot move the next state out
     next_state: Option<Box<dyn State<'s> + 's>>,
-    time_left: Duration,
+    transition: Transition,
+    elapsed: Duration,
+    /// The targets the previous and next states are rendered to, kept across frames as long as
+    /// the window is not resized.
+    render_textures: RefCell<Option<(RenderTexture, RenderTexture)>>,
 }
 
 impl<'s> Transitioning<'s> {
-    const TRANSITION_TIME: Duration = Duration::from_millis(200);
     pub fn new(
         _assets: &'s AssetManager,
+        transition: Transition,
         prev_state: impl State<'s> + 's,
         next_state: impl State<'s> + 's,
     ) -> anyhow::Result<Self> {
         Ok(Self {
             prev_state: Box::new(prev_state),
             next_state: Some(Box::new(next_state)),
-            time_left: Self::TRANSITION_TIME,
+            transition,
+            elapsed: Duration::ZERO,
+            render_textures: RefCell::new(None),
         })
     }
+
+    /// How far along the transition is, from 0 to 1, after easing.
+    fn progress(&self) -> f32 {
+        let t = if self.transition.duration.is_zero() {
+            1.
+        } else {
+            (self.elapsed.as_secs_f32() / self.transition.duration.as_secs_f32()).min(1.)
+        };
+
+        self.transition.easing.apply(t)
+    }
+
+    fn direction_vector(direction: Direction) -> Vector2f {
+        let direction = Vector2i::from(direction);
+        Vector2f::new(direction.x as f32, direction.y as f32)
+    }
+
+    /// The part of a screen of the given size that a wipe towards a direction has swept over.
+    fn wiped_rect(direction: Direction, size: Vector2f, t: f32) -> FloatRect {
+        match direction {
+            Direction::East => FloatRect::new(0., 0., size.x * t, size.y),
+            Direction::West => FloatRect::new(size.x * (1. - t), 0., size.x * t, size.y),
+            Direction::South => FloatRect::new(0., 0., size.x, size.y * t),
+            Direction::North => FloatRect::new(0., size.y * (1. - t), size.x, size.y * t),
+        }
+    }
+
+    fn create_render_texture(size: Vector2u) -> RenderTexture {
+        RenderTexture::new(size.x, size.y).expect("could not create transition render texture")
+    }
 }
 
 impl<'s> State<'s> for Transitioning<'s> {
@@ -42,10 +156,9 @@ impl<'s> State<'s> for Transitioning<'s> {
         ctx: &mut Context<'s>,
         _window: &mut RenderWindow,
     ) -> ControlFlow<Box<dyn State<'s> + 's>, ()> {
-        // Update time left on transition
-        self.time_left = self.time_left.saturating_sub(ctx.delta_time);
+        self.elapsed += ctx.delta_time.mul_f32(ctx.animation_speed());
 
-        if self.time_left.is_zero() {
+        if self.elapsed >= self.transition.duration {
             ControlFlow::Break(self.next_state.take().unwrap())
         } else {
             ControlFlow::Continue(())
@@ -62,29 +175,96 @@ impl<'s> State<'s> for Transitioning<'s> {
     }
 
     fn draw(&self, ctx: &mut Context<'s>, target: &mut dyn RenderTarget) {
-        let mut render_target = RenderTexture::new(target.size().x, target.size().y).unwrap();
+        let size = target.size();
+        let size_f = Vector2f::new(size.x as f32, size.y as f32);
 
-        self.next_state
+        let mut render_textures = self.render_textures.borrow_mut();
+        if render_textures
             .as_ref()
-            .unwrap()
-            .draw(ctx, &mut render_target);
-
-        let mut overlay_sprite = Sprite::with_texture_and_rect(
-            render_target.texture(),
-            Rect {
-                width: target.size().x as i32,
-                height: -(target.size().y as i32),
-                top: target.size().y as i32,
-                ..Default::default()
-            },
-        );
-
-        let transition_alpha = (255.
-            - (self.time_left.as_secs_f32() / Self::TRANSITION_TIME.as_secs_f32()) * 255.)
-            as u8;
-        overlay_sprite.set_color(Color::rgba(255, 255, 255, transition_alpha));
-
-        self.prev_state.draw(ctx, target);
-        target.draw(&overlay_sprite);
+            .is_none_or(|(prev, _)| prev.size() != size)
+        {
+            *render_textures = Some((
+                Self::create_render_texture(size),
+                Self::create_render_texture(size),
+            ));
+        }
+        let (prev_texture, next_texture) = render_textures.as_mut().unwrap();
+
+        self.prev_state.draw(ctx, prev_texture);
+        prev_texture.display();
+        self.next_state.as_ref().unwrap().draw(ctx, next_texture);
+        next_texture.display();
+
+        let mut prev = Sprite::with_texture(prev_texture.texture());
+        let mut next = Sprite::with_texture(next_texture.texture());
+        let t = self.progress();
+
+        match self.transition.style {
+            TransitionStyle::Crossfade => {
+                next.set_color(Color::rgba(255, 255, 255, (t * 255.) as u8));
+                target.draw(&prev);
+                target.draw(&next);
+            }
+            TransitionStyle::FadeThroughColor(color) => {
+                let (sprite, color_alpha) = if t < 0.5 {
+                    (&prev, t * 2.)
+                } else {
+                    (&next, (1. - t) * 2.)
+                };
+                target.draw(sprite);
+
+                let mut overlay = RectangleShape::with_size(size_f);
+                overlay.set_fill_color(Color {
+                    a: (color.a as f32 * color_alpha) as u8,
+                    ..color
+                });
+                target.draw(&overlay);
+            }
+            TransitionStyle::Slide(direction) => {
+                let offset = Self::direction_vector(direction).cwise_mul(size_f);
+                prev.set_position(offset * t);
+                next.set_position(offset * t - offset);
+                target.draw(&prev);
+                target.draw(&next);
+            }
+            TransitionStyle::Wipe(direction) => {
+                let rect = Self::wiped_rect(direction, size_f, t);
+                next.set_texture_rect(IntRect::new(
+                    rect.left as i32,
+                    rect.top as i32,
+                    rect.width.ceil() as i32,
+                    rect.height.ceil() as i32,
+                ));
+                next.set_position(Vector2f::new(rect.left, rect.top));
+                target.draw(&prev);
+                target.draw(&next);
+            }
+            TransitionStyle::Iris { center } => {
+                // Grow until the circle covers the corner furthest away from its center
+                let max_radius = [
+                    Vector2f::new(0., 0.),
+                    Vector2f::new(size_f.x, 0.),
+                    Vector2f::new(0., size_f.y),
+                    size_f,
+                ]
+                .into_iter()
+                .map(|corner| (corner - center).length_sq().sqrt())
+                .fold(0., f32::max);
+                let radius = max_radius * t;
+
+                let mut iris = CircleShape::new(radius, 64);
+                iris.set_origin(Vector2f::new(radius, radius));
+                iris.set_position(center);
+                iris.set_texture(next_texture.texture(), false);
+                iris.set_texture_rect(IntRect::new(
+                    (center.x - radius) as i32,
+                    (center.y - radius) as i32,
+                    (radius * 2.) as i32,
+                    (radius * 2.) as i32,
+                ));
+                target.draw(&prev);
+                target.draw(&iris);
+            }
+        }
     }
 }
@@ -0,0 +1,326 @@
+//! A frontend that plays levels in a terminal using text characters and ANSI colors, for machines
+//! with no display to open a window on. Levels follow the same rules and share the same save data
+//! as the windowed game.
+//!
+//! Levels are still built out of SFML types, so the SFML and CSFML shared libraries must be
+//! installed wherever this runs. No display, graphics context nor audio device is needed, though:
+//! the level tileset is loaded without its texture and no sounds are played.
+
+use std::io::{self, BufRead, Write};
+
+use anyhow::Context as AnyCtx;
+use sfml::system::Vector2i;
+
+use crate::{
+    assets::{AssetManager, LevelCategory},
+    context::SaveData,
+    graphics::Tilesheet,
+    level::{
+        objects::{AcceptedCrateStyle, CrateStyle},
+        tilemap::LevelTile,
+        Direction, Level,
+    },
+};
+
+/// The ANSI foreground colors crate styles are drawn with, cycling for styles past the last one.
+const STYLE_COLORS: [u8; 6] = [31, 32, 34, 33, 35, 36];
+
+const HELP: &str = "w/a/s/d: move, W/A/S/D: pull (where allowed), u: undo, r: restart, q: quit. \
+                    Several commands may be entered at once, e.g. `ddwu`.";
+
+/// Wraps text in ANSI escape codes, unless colors are disabled.
+struct Painter {
+    enabled: bool,
+}
+
+impl Painter {
+    /// Creates a painter that honors the `NO_COLOR` convention.
+    fn from_env() -> Self {
+        Self {
+            enabled: std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()),
+        }
+    }
+
+    fn paint(&self, codes: &str, text: &str) -> String {
+        if self.enabled && !codes.is_empty() {
+            format!("\x1b[{}m{}\x1b[0m", codes, text)
+        } else {
+            text.to_owned()
+        }
+    }
+
+    fn clear_screen(&self) {
+        if self.enabled {
+            print!("\x1b[2J\x1b[H");
+        }
+    }
+}
+
+fn style_color(style: CrateStyle) -> u8 {
+    STYLE_COLORS[(style.id() as usize - 1) % STYLE_COLORS.len()]
+}
+
+/// What the player chose to do once a level has been left.
+enum LevelOutcome {
+    Won,
+    Quit,
+}
+
+/// Run the terminal frontend, returning on failure or once the player quits.
+pub fn run() -> anyhow::Result<()> {
+    env_logger::init();
+
+    let level_categories = LevelCategory::load_all().context("failed to load levels")?;
+    let tilesheet = Tilesheet::headless_from_tileset(AssetManager::load_level_tileset()?)
+        .context("failed to load level tileset")?;
+    let mut save_data = match SaveData::from_savefile() {
+        Ok(x) => x,
+        Err(err) => {
+            log::warn!("could not load save data, creating new one: {}", err);
+            SaveData::default()
+        }
+    };
+
+    let painter = Painter::from_env();
+    let mut input = io::stdin().lock();
+
+    loop {
+        print_level_list(&painter, &level_categories, &save_data);
+        let Some(line) = prompt(&mut input, "Choose a level (e.g. 1.1), or q to quit: ")? else {
+            return Ok(());
+        };
+        if line == "q" {
+            return Ok(());
+        }
+
+        let Some((mut category_idx, mut level_idx)) = parse_level_choice(&line) else {
+            println!("Levels are chosen as <category>.<level>, e.g. 1.1");
+            continue;
+        };
+        let Some(category) = level_categories.get(category_idx) else {
+            println!("There is no category {}", category_idx + 1);
+            continue;
+        };
+        if level_idx >= category.maps.len() {
+            println!("There is no level {} in {}", level_idx + 1, category.name);
+            continue;
+        }
+        if !category.is_unlocked(level_idx, &save_data) {
+            println!("That level is still locked");
+            continue;
+        }
+
+        // Keep on playing the levels of the category in order until the player quits
+        loop {
+            let (map, path) = &level_categories[category_idx].maps[level_idx];
+            let level = Level::from_map_and_tilesheet(map, &tilesheet)
+                .with_context(|| format!("failed to load level {:?}", path))?;
+
+            match play_level(&painter, &mut input, level, &mut save_data, path.clone())? {
+                LevelOutcome::Won if level_idx + 1 < level_categories[category_idx].maps.len() => {
+                    level_idx += 1;
+                }
+                LevelOutcome::Won if category_idx + 1 < level_categories.len() => {
+                    category_idx += 1;
+                    level_idx = 0;
+                }
+                LevelOutcome::Won | LevelOutcome::Quit => break,
+            }
+        }
+    }
+}
+
+/// Parses a one-based `<category>.<level>` pair into zero-based indices.
+fn parse_level_choice(line: &str) -> Option<(usize, usize)> {
+    let (category, level) = line.split_once('.')?;
+    let category = category.trim().parse::<usize>().ok()?.checked_sub(1)?;
+    let level = level.trim().parse::<usize>().ok()?.checked_sub(1)?;
+    Some((category, level))
+}
+
+/// Prints `message` and reads a trimmed line from `input`, or `None` once input is over.
+fn prompt(input: &mut impl BufRead, message: &str) -> anyhow::Result<Option<String>> {
+    print!("{}", message);
+    io::stdout().flush()?;
+
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.trim().to_owned()))
+}
+
+fn print_level_list(painter: &Painter, level_categories: &[LevelCategory], save_data: &SaveData) {
+    println!();
+    for (category_idx, category) in level_categories.iter().enumerate() {
+        println!(
+            "{}. {}",
+            category_idx + 1,
+            painter.paint("1", &category.name)
+        );
+        for (level_idx, (_, path)) in category.maps.iter().enumerate() {
+            let status = if save_data.internal_set().contains(path) {
+                let stars = save_data.stars(path).unwrap_or(0) as usize;
+                painter.paint("32", &format!("done {}", "*".repeat(stars)))
+            } else if category.is_unlocked(level_idx, save_data) {
+                String::new()
+            } else {
+                painter.paint("2", "locked")
+            };
+            println!("   {}.{} {}", category_idx + 1, level_idx + 1, status);
+        }
+    }
+}
+
+/// Plays a level until it is won or the player quits, recording its completion on a win.
+fn play_level(
+    painter: &Painter,
+    input: &mut impl BufRead,
+    mut level: Level,
+    save_data: &mut SaveData,
+    path: std::path::PathBuf,
+) -> anyhow::Result<LevelOutcome> {
+    let initial_level = level.clone();
+    let mut message = HELP.to_owned();
+
+    loop {
+        painter.clear_screen();
+        print_level(painter, &level);
+        println!("{}", status_line(&level));
+        println!("{}", message);
+        message.clear();
+
+        if level.is_won() && !level.is_failed() {
+            if let Some(stars) = level.star_rating() {
+                save_data.record_stars(path.clone(), stars);
+            }
+            save_data.complete_lvl(path);
+
+            let stars = level
+                .star_rating()
+                .map(|stars| format!(" {}", "*".repeat(stars as usize)))
+                .unwrap_or_default();
+            println!(
+                "{}",
+                painter.paint("1;32", &format!("Level complete!{}", stars))
+            );
+            prompt(input, "Press enter to continue")?;
+            return Ok(LevelOutcome::Won);
+        }
+        if level.is_failed() {
+            println!(
+                "{}",
+                painter.paint("1;31", "Out of moves! Undo (u) or restart (r).")
+            );
+        }
+
+        let Some(line) = prompt(input, "> ")? else {
+            return Ok(LevelOutcome::Quit);
+        };
+
+        for command in line.chars() {
+            let direction = match command.to_ascii_lowercase() {
+                'w' => Some(Direction::North),
+                'a' => Some(Direction::West),
+                's' => Some(Direction::South),
+                'd' => Some(Direction::East),
+                _ => None,
+            };
+
+            match (command, direction) {
+                (_, Some(direction)) => {
+                    if level.is_won() || level.is_failed() {
+                        break;
+                    }
+                    let pull = command.is_ascii_uppercase();
+                    if pull && !level.pulling_enabled() {
+                        message = "Pulling is not allowed in this level".to_owned();
+                        break;
+                    }
+                    level.step(direction, pull);
+                }
+                ('u', _) => {
+                    level.undo_step();
+                }
+                ('r', _) => level = initial_level.clone(),
+                ('q', _) => return Ok(LevelOutcome::Quit),
+                ('?' | 'h', _) => message = HELP.to_owned(),
+                (' ', _) => (),
+                (other, _) => {
+                    message = format!("Unknown command `{}`. {}", other, HELP);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+fn status_line(level: &Level) -> String {
+    let mut status = format!("Moves: {}", level.action_count());
+    if let Some(par_moves) = level.par_moves() {
+        status += &format!("  Par: {}", par_moves);
+    }
+    if let Some(max_moves) = level.max_moves() {
+        status += &format!("  Max: {}", max_moves);
+    }
+    if level.pulling_enabled() {
+        status += &format!("  Pulls: {}", level.pull_count());
+    }
+    status
+}
+
+fn print_level(painter: &Painter, level: &Level) {
+    let size = level.tilemap().size();
+    for y in 0..size.y as i32 {
+        let row = (0..size.x as i32)
+            .map(|x| cell_glyph(painter, level, Vector2i::new(x, y)))
+            .collect::<String>();
+        println!("{}", row.trim_end());
+    }
+}
+
+/// The character to draw a level cell as, along with its color.
+fn cell_glyph(painter: &Painter, level: &Level, cell: Vector2i) -> String {
+    if level.player().position() == cell {
+        return painter.paint("1", "@");
+    }
+
+    let goal = level.goals().iter().find(|g| g.position() == cell);
+    if let Some(c) = level
+        .crates()
+        .iter()
+        .find(|c| c.occupies(cell) && !c.in_hole())
+    {
+        let is_positioned =
+            goal.is_some_and(|g| g.is_done() && g.accepted_style().accepts(c.style()));
+        let glyph = if is_positioned { "*" } else { "$" };
+        return painter.paint(&format!("1;{}", style_color(c.style())), glyph);
+    }
+
+    if let Some(goal) = goal {
+        return match goal.accepted_style() {
+            AcceptedCrateStyle::Specific(style) => {
+                painter.paint(&style_color(style).to_string(), ".")
+            }
+            AcceptedCrateStyle::Any => ".".to_owned(),
+        };
+    }
+
+    match level.tilemap().get_tile(cell) {
+        Some(LevelTile::Solid) => painter.paint("2", "#"),
+        Some(LevelTile::Hole { depth }) => {
+            let crates_in_hole = level
+                .crates()
+                .iter()
+                .filter(|c| c.occupies(cell) && c.in_hole())
+                .count() as u32;
+            match depth.saturating_sub(crates_in_hole) {
+                0 => painter.paint("2", "="),
+                1 => "o".to_owned(),
+                remaining => remaining.to_string(),
+            }
+        }
+        Some(LevelTile::Paint(style)) => painter.paint(&style_color(style).to_string(), "+"),
+        Some(LevelTile::Floor) | None => " ".to_owned(),
+    }
+}
@@ -1,6 +1,6 @@
 use sfml::{
     audio::{Sound, SoundSource},
-    graphics::{Color, Drawable, RenderTarget, RenderWindow, Sprite, Text, Transformable},
+    graphics::{Color, Drawable, Sprite, Text, Transform, Transformable},
     system::{Vector2f, Vector2u},
 };
 use thiserror::Error;
@@ -54,7 +54,7 @@ pub fn get_ui_obj_from_tiled_obj<'s>(
         } else {
             text.clone()
         };
-        let mut text = Text::new(&text, &assets.win_font, *pixel_size as u32);
+        let mut text = Text::new(&text, context.font(), *pixel_size as u32);
         text.set_fill_color(Color::rgb(color.red, color.green, color.blue));
         let bounds = text.local_bounds();
         text.set_position(Vector2f::new(object.x, object.y));
@@ -88,7 +88,7 @@ pub fn get_ui_obj_from_tiled_obj<'s>(
         Ok(Box::new(text))
     } else if object.tile_data().is_some() {
         // Static icon
-        Ok(Box::new(sprite_from_tiled_obj(context.assets, object)?))
+        Ok(Box::new(sprite_from_tiled_obj(context, object)?))
     } else {
         Err(anyhow::anyhow!(
             "could not obtain ui object from tiled object {:?}",
@@ -106,7 +106,7 @@ pub enum SpriteFromTiledObjError {
 }
 
 pub fn sprite_from_tiled_obj<'s>(
-    assets: &'s AssetManager,
+    context: &Context<'s>,
     object: &tiled::Object,
 ) -> Result<Sprite<'s>, SpriteFromTiledObjError> {
     let tile = object
@@ -114,8 +114,8 @@ pub fn sprite_from_tiled_obj<'s>(
         .ok_or(SpriteFromTiledObjError::NoTileAttached)?;
     let tileset = &tile.get_tileset().name;
     let tilesheet = match tileset.as_str() {
-        "icons" => &assets.icon_tilesheet,
-        "Sokoban" => &assets.tilesheet,
+        "icons" => &context.assets.icon_tilesheet,
+        "Sokoban" => context.tilesheet(),
         x => return Err(SpriteFromTiledObjError::InvalidTilesheetName(x.to_owned())),
     };
     let mut sprite = tilesheet
@@ -135,6 +135,32 @@ pub fn sprite_from_tiled_obj<'s>(
     Ok(sprite)
 }
 
+/// Creates a row of three star icons starting at the given position, the first `stars` of which
+/// are highlighted.
+pub fn star_rating_sprites<'s>(
+    assets: &'s AssetManager,
+    stars: u8,
+    position: Vector2f,
+    star_size: f32,
+) -> Vec<Sprite<'s>> {
+    const STAR_ICON_ID: u32 = 101;
+
+    (0..3u8)
+        .map(|i| {
+            let mut star = assets.icon_tilesheet.tile_sprite(STAR_ICON_ID).unwrap();
+            let scale = star_size / star.global_bounds().height;
+            star.set_scale(Vector2f::new(scale, scale));
+            star.set_position(position + Vector2f::new(star_size * i as f32, 0.));
+            star.set_color(if i < stars {
+                Color::rgb(0xff, 0xcc, 0x00)
+            } else {
+                Color::rgba(0xff, 0xff, 0xff, 80)
+            });
+            star
+        })
+        .collect()
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ButtonState {
     Pressed,
@@ -142,30 +168,28 @@ pub enum ButtonState {
     Inactive,
 }
 
-pub fn update_button(ctx: &mut Context, window: &RenderWindow, sprite: &mut Sprite) -> ButtonState {
-    let transform = camera_transform(
-        window.size(),
+/// The transform used to lay out UI objects on a target of the given size.
+pub fn ui_transform(assets: &AssetManager, target_size: Vector2u) -> Transform {
+    camera_transform(
+        target_size,
         Vector2u::new(
-            ctx.assets.play_overlay_map.width * ctx.assets.play_overlay_map.tile_width,
-            ctx.assets.play_overlay_map.height * ctx.assets.play_overlay_map.tile_height,
+            assets.play_overlay_map.width * assets.play_overlay_map.tile_width,
+            assets.play_overlay_map.height * assets.play_overlay_map.tile_height,
         ),
         0.,
-    );
-
-    let mouse_pos = window.mouse_position();
-    let mouse_pos = transform
-        .inverse()
-        .transform_point(Vector2f::new(mouse_pos.x as f32, mouse_pos.y as f32));
+    )
+}
 
+pub fn update_button(ctx: &mut Context, sprite: &mut Sprite) -> ButtonState {
     let mut color = sprite.color();
 
-    if sprite.global_bounds().contains(mouse_pos) {
+    if ctx.input.mouse().is_hovering_ui(sprite.global_bounds()) {
         color.a = 0xcf;
         sprite.set_color(color);
 
         if ctx.input.just_released_lmb() {
             let mut sound = Sound::with_buffer(&ctx.assets.ui_click_sound);
-            sound.set_volume(60.);
+            sound.set_volume(ctx.config.ui_volume);
             sound.play();
             ctx.sound.add_sound(sound);
             ButtonState::Pressed